@@ -0,0 +1,91 @@
+//! In-memory cache of `git-upload-pack` ref advertisements.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+
+/// Caches the `info/refs` response of the git backend for `git-upload-pack`, keyed on
+/// namespace and git protocol version, so that clone and fetch negotiations don't spawn a
+/// backend process each.
+///
+/// Entries are invalidated when a push to their namespace completes. Since refs may also be
+/// updated without going through the server, eg. by replication, entries expire after `ttl`.
+#[derive(Debug, Clone)]
+pub struct AdvertisementCache {
+    ttl: Duration,
+    entries: Arc<Mutex<HashMap<(String, String), Advertisement>>>,
+}
+
+/// A ref advertisement, as returned by the git backend.
+#[derive(Debug, Clone)]
+pub struct Advertisement {
+    /// Response headers.
+    pub headers: HashMap<String, Vec<String>>,
+    /// Response body.
+    pub body: Bytes,
+    /// When the advertisement was generated.
+    created: Instant,
+}
+
+impl AdvertisementCache {
+    /// Create a new cache, with entries expiring after `ttl`.
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: Default::default(),
+        }
+    }
+
+    /// Get the advertisement of a namespace for the given protocol, if it is cached and
+    /// hasn't expired.
+    pub fn get(&self, namespace: &str, protocol: &str) -> Option<Advertisement> {
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("advertisement cache lock is not poisoned");
+        let key = (namespace.to_owned(), protocol.to_owned());
+
+        match entries.get(&key) {
+            Some(ad) if ad.created.elapsed() < self.ttl => Some(ad.clone()),
+            Some(_) => {
+                entries.remove(&key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Cache the advertisement of a namespace for the given protocol.
+    pub fn insert(
+        &self,
+        namespace: &str,
+        protocol: &str,
+        headers: HashMap<String, Vec<String>>,
+        body: Bytes,
+    ) {
+        let mut entries = self
+            .entries
+            .lock()
+            .expect("advertisement cache lock is not poisoned");
+
+        // Drop expired entries, so that namespaces that are no longer fetched don't linger.
+        entries.retain(|_, ad| ad.created.elapsed() < self.ttl);
+        entries.insert(
+            (namespace.to_owned(), protocol.to_owned()),
+            Advertisement {
+                headers,
+                body,
+                created: Instant::now(),
+            },
+        );
+    }
+
+    /// Invalidate the advertisements of a namespace, eg. after its refs were updated.
+    pub fn invalidate(&self, namespace: &str) {
+        self.entries
+            .lock()
+            .expect("advertisement cache lock is not poisoned")
+            .retain(|(ns, _), _| ns != namespace);
+    }
+}
@@ -16,6 +16,18 @@ pub enum Error {
     #[error("service '{0}' not available")]
     ServiceUnavailable(&'static str),
 
+    /// Too many git backends are running concurrently.
+    #[error("too many concurrent git backends")]
+    Busy,
+
+    /// Too many requests were made by the remote.
+    #[error("rate limit exceeded for '{0}'")]
+    RateLimited(&'static str),
+
+    /// The request body is larger than allowed.
+    #[error("request body exceeds the maximum of {0} bytes")]
+    PayloadTooLarge(u64),
+
     /// HTTP error.
     #[error("HTTP error: {0}")]
     Http(#[from] http::Error),
@@ -147,10 +159,14 @@ impl Error {
         match self {
             Error::UnsupportedContentEncoding(_) => http::StatusCode::NOT_IMPLEMENTED,
             Error::ServiceUnavailable(_) => http::StatusCode::SERVICE_UNAVAILABLE,
+            Error::Busy => http::StatusCode::SERVICE_UNAVAILABLE,
+            Error::RateLimited(_) => http::StatusCode::TOO_MANY_REQUESTS,
+            Error::PayloadTooLarge(_) => http::StatusCode::PAYLOAD_TOO_LARGE,
             Error::Unauthorized(_) => http::StatusCode::UNAUTHORIZED,
             Error::KeyMismatch { .. } => http::StatusCode::UNAUTHORIZED,
             Error::AliasNotFound => http::StatusCode::NOT_FOUND,
             Error::InvalidId => http::StatusCode::NOT_FOUND,
+            Error::NamespaceNotFound => http::StatusCode::NOT_FOUND,
             _ => http::StatusCode::INTERNAL_SERVER_ERROR,
         }
     }
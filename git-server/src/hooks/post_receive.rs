@@ -2,11 +2,14 @@
 //!
 //! <https://git-scm.com/docs/githooks#post-receive>
 //!
+use std::collections::HashMap;
 use std::io::prelude::*;
-use std::io::{stdin, ErrorKind, Write};
+use std::io::{self, stdin, ErrorKind, Write};
+use std::os::unix::net::UnixStream;
 use std::path::Path;
 use std::str;
 use std::str::FromStr;
+use std::time::Duration;
 
 use either::Either;
 use envconfig::Envconfig;
@@ -20,12 +23,24 @@ use librad::git::Urn;
 use librad::paths::Paths;
 use librad::profile::Profile;
 use librad::PeerId;
+use serde_json::json;
 
 use super::storage::Storage;
 use super::{types::ReceivePackEnv, CertSignerDetails};
 use crate::error::Error;
 
 pub const RAD_ID_REF: &str = "rad/id";
+/// How long to wait for the receive socket to accept an event.
+pub const RECEIVE_SOCKET_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// Outcome of checking delegate agreement on the default branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Quorum {
+    /// A majority of delegates agree; `branch_ref` is one of the agreeing delegates' refs.
+    Reached { branch_ref: String, votes: usize },
+    /// No commit is shared by a majority of delegates.
+    NotReached { votes: usize },
+}
 
 /// `PostReceive` provides access to the standard input values passed into the `post-receive`
 /// git hook, as well as parses environmental variables that may be used to process the hook.
@@ -115,13 +130,25 @@ impl PostReceive {
 
                 post_receive.track_identity(Some(peer_id))?;
                 post_receive.update_identity(&repo)?;
-                post_receive.receive_hook()?;
+
+                // The refs are already updated at this point, so the event is sent even if
+                // the receive hook fails.
+                let result = post_receive.receive_hook();
+                post_receive.receive_socket(Some(&peer_id));
+                result?;
             }
         } else {
             println!("Pushing new identity...");
 
             post_receive.initialize_identity(&repo)?;
             post_receive.track_identity(None)?;
+
+            let peer_id = post_receive
+                .updates
+                .first()
+                .and_then(|(refname, _, _)| crate::parse_ref(refname).ok())
+                .map(|(peer_id, _)| peer_id);
+            post_receive.receive_socket(peer_id.as_ref());
         }
 
         Ok(())
@@ -133,53 +160,166 @@ impl PostReceive {
         if let Some(default_branch) = &self.env.default_branch {
             let suffix = format!("heads/{}", default_branch);
 
-            for (refname, from, _) in self.updates.iter() {
+            for (refname, from, to) in self.updates.iter() {
                 let (peer_id, rest) = crate::parse_ref(refname)?;
 
-                if from.is_zero() {
+                if to.is_zero() {
                     println!("Deleted ref {} for {}", rest, peer_id);
+                } else if from.is_zero() {
+                    println!("Created ref {} for {}", rest, peer_id);
                 } else {
                     println!("Updated ref {} for {}", rest, peer_id);
                 }
 
-                // Only delegates can update HEAD.
+                let branch = if let Some(branch) = rest.strip_prefix("heads/") {
+                    branch
+                } else {
+                    continue;
+                };
+                // Any authorized pusher gets a HEAD for their own remote, so that
+                // their branches can be browsed and cloned.
+                if rest == suffix && !to.is_zero() {
+                    self.set_remote_head(&peer_id, default_branch, repo)?;
+                }
+
+                // Only delegates can update the canonical branches and HEAD.
                 if !self.delegates.contains(&peer_id) {
                     continue;
                 }
-                // For now, we only create a ref for the default branch.
                 if rest != suffix {
+                    // Non-default branches are only mirrored for single-delegate projects,
+                    // since there is no quorum to resolve them against otherwise.
+                    if self.delegates.len() == 1 {
+                        self.set_local_branch(branch, *to, repo)?;
+                    }
+                    continue;
+                }
+                if to.is_zero() {
+                    println!("Default branch deleted, not updating HEAD.");
                     continue;
                 }
                 println!("Update to default branch detected, setting HEAD...");
 
-                // TODO: This should only update when a quorum is reached between delegates.
-                // For a single delegate, we can just always allow it.
+                // For a single delegate, we can just always allow it. Otherwise, only update
+                // when a majority of delegates agree on the branch tip.
                 if self.delegates.len() == 1 {
                     self.set_head(refname.as_str(), default_branch, repo)?;
                 } else {
-                    println!("Cannot set head for multi-delegate project: not supported.");
+                    match self.head_quorum(default_branch, repo)? {
+                        Quorum::Reached { branch_ref, votes } => {
+                            println!(
+                                "Quorum reached with {}/{} delegates.",
+                                votes,
+                                self.delegates.len()
+                            );
+                            self.set_head(&branch_ref, default_branch, repo)?;
+                        }
+                        Quorum::NotReached { votes } => {
+                            println!(
+                                "Quorum not reached: {}/{} delegates agree on {}, not setting HEAD.",
+                                votes,
+                                self.delegates.len(),
+                                default_branch
+                            );
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Mirror a delegate branch to the project's canonical `refs/heads/<branch>`, or remove
+    /// it if the branch was deleted.
+    fn set_local_branch(&self, branch: &str, oid: Oid, repo: &Repository) -> Result<(), Error> {
+        let local_branch_ref = self.namespace_ref(&format!("heads/{}", branch));
+
+        if oid.is_zero() {
+            match repo.find_reference(&local_branch_ref) {
+                Ok(mut reference) => {
+                    println!("Deleting ref {:?}", local_branch_ref);
+                    reference.delete()?;
                 }
-                // TODO
-                //
-                // For non-default-branch refs, we can add them as:
-                //
-                // `refs/remotes/cloudhead@<peer-id>/<branch>`
+                Err(err) if err.code() == git2::ErrorCode::NotFound => {}
+                Err(err) => return Err(err.into()),
             }
+        } else {
+            println!("Setting ref {:?} -> {:?}", local_branch_ref, oid);
+            repo.reference(
+                &local_branch_ref,
+                oid,
+                true,
+                &format!("set-local-branch ({})", self.key_fingerprint),
+            )?;
         }
+        Ok(())
+    }
+
+    /// Point a peer's remote `HEAD` to its copy of the default branch.
+    ///
+    /// eg. `refs/remotes/<peer>/HEAD -> refs/remotes/<peer>/heads/master`
+    fn set_remote_head(
+        &self,
+        peer_id: &PeerId,
+        branch: &str,
+        repo: &Repository,
+    ) -> Result<(), git2::Error> {
+        let head_ref = self.namespace_ref(&format!("remotes/{}/HEAD", peer_id));
+        let branch_ref = self.namespace_ref(&format!("remotes/{}/heads/{}", peer_id, branch));
+
+        println!("Setting ref {:?} -> {:?}", head_ref, branch_ref);
+        repo.reference_symbolic(&head_ref, &branch_ref, true, "set-remote-head (radicle)")?;
 
         Ok(())
     }
 
+    /// Check whether a majority of delegates have published the same tip for the given branch.
+    ///
+    /// Reads each delegate's `refs/remotes/<peer>/heads/<branch>` and counts the delegates
+    /// pointing to each commit.
+    fn head_quorum(&self, branch: &str, repo: &Repository) -> Result<Quorum, git2::Error> {
+        let mut votes: HashMap<Oid, (usize, String)> = HashMap::new();
+
+        for delegate in &self.delegates {
+            let remote_ref = format!("remotes/{}/heads/{}", delegate, branch);
+            let reference = match repo.find_reference(&self.namespace_ref(&remote_ref)) {
+                Ok(reference) => reference,
+                Err(err) if err.code() == git2::ErrorCode::NotFound => continue,
+                Err(err) => return Err(err),
+            };
+
+            if let Some(oid) = reference.target() {
+                votes
+                    .entry(oid)
+                    .or_insert((0, format!("refs/{}", remote_ref)))
+                    .0 += 1;
+            }
+        }
+
+        let quorum = self.delegates.len() / 2 + 1;
+        let best = votes.into_values().max_by_key(|(count, _)| *count);
+
+        match best {
+            Some((votes, branch_ref)) if votes >= quorum => {
+                Ok(Quorum::Reached { branch_ref, votes })
+            }
+            Some((votes, _)) => Ok(Quorum::NotReached { votes }),
+            None => Ok(Quorum::NotReached { votes: 0 }),
+        }
+    }
+
     /// Set the 'HEAD' of a project.
     ///
     /// Creates the necessary refs so that a `git clone` may succeed and checkout the correct
-    /// branch.
+    /// branch. If the branch ref doesn't point directly to a commit, eg. because it is
+    /// symbolic, `HEAD` is left untouched and `None` is returned.
     fn set_head(
         &self,
         branch_ref: &str,
         branch: &str,
         repo: &Repository,
-    ) -> Result<git2::Oid, git2::Error> {
+    ) -> Result<Option<git2::Oid>, git2::Error> {
         let urn = &self.urn;
         let namespace = urn.encode_id();
 
@@ -190,7 +330,16 @@ impl PostReceive {
         // eg. refs/namespaces/<namespace>/refs/remotes/<peer>/heads/master
         let branch_ref = format!("{}/{}", namespace_path, branch_ref);
         let reference = repo.find_reference(&branch_ref)?;
-        let oid = reference.target().expect("reference target must exist");
+        let oid = match reference.target() {
+            Some(oid) => oid,
+            None => {
+                println!(
+                    "Reference {:?} has no direct target, not setting HEAD for {}.",
+                    branch_ref, urn
+                );
+                return Ok(None);
+            }
+        };
 
         // eg. refs/namespaces/<namespace>/HEAD
         let head_ref = format!("{}/HEAD", namespace_path);
@@ -203,7 +352,7 @@ impl PostReceive {
         println!("Setting ref {:?} -> {:?}", head_ref, local_branch_ref);
         repo.reference_symbolic(&head_ref, local_branch_ref, true, "set-head (radicle)")?;
 
-        Ok(oid)
+        Ok(Some(oid))
     }
 
     fn update_identity(&mut self, repo: &Repository) -> Result<(), Error> {
@@ -369,6 +518,60 @@ impl PostReceive {
         Ok(())
     }
 
+    /// Notify the configured unix socket of the push, with a single JSON line.
+    ///
+    /// Eg. `{"urn": "rad:git:...", "peer": "hyn...", "updates": [{"refname": ..., "old": ..., "new": ...}]}`
+    ///
+    /// The push has already been applied, so errors are only reported, never returned.
+    pub fn receive_socket(&self, peer_id: Option<&PeerId>) {
+        let path = if let Some(path) = &self.env.receive_socket {
+            path
+        } else {
+            return;
+        };
+        println!("Notifying receive socket...");
+
+        match self.send_event(path, peer_id) {
+            Ok(()) => {}
+            Err(err)
+                if matches!(
+                    err.kind(),
+                    ErrorKind::NotFound | ErrorKind::ConnectionRefused
+                ) =>
+            {
+                println!("Receive socket not found in {:?}, skipping...", path);
+            }
+            Err(err) => {
+                eprintln!("Failed to notify receive socket {:?}: {}", path, err);
+            }
+        }
+    }
+
+    /// Write the push event to the unix socket at the given path.
+    fn send_event(&self, path: &Path, peer_id: Option<&PeerId>) -> io::Result<()> {
+        let mut stream = UnixStream::connect(path)?;
+        // Don't hold up the push if the listener isn't reading.
+        stream.set_write_timeout(Some(RECEIVE_SOCKET_TIMEOUT))?;
+
+        let updates = self
+            .updates
+            .iter()
+            .map(|(refname, old, new)| {
+                json!({
+                    "refname": refname,
+                    "old": old.to_string(),
+                    "new": new.to_string(),
+                })
+            })
+            .collect::<Vec<_>>();
+        let event = json!({
+            "urn": self.urn.to_string(),
+            "peer": peer_id.map(|p| p.default_encoding()),
+            "updates": updates,
+        });
+        writeln!(&mut stream, "{}", event)
+    }
+
     pub fn receive_hook(&self) -> Result<(), Error> {
         use std::process::{Command, Stdio};
 
@@ -419,3 +622,54 @@ impl PostReceive {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env, fs, process};
+
+    #[test]
+    fn test_set_head_symbolic_ref() {
+        let root = env::temp_dir()
+            .join("rad")
+            .join("git-server")
+            .join(format!("post-receive-{}", process::id()));
+        fs::remove_dir_all(&root).ok();
+
+        let paths = Paths::from_root(&root).unwrap();
+        let repo = Repository::init_bare(paths.git_dir()).unwrap();
+        let signature = git2::Signature::now("radicle", "radicle@localhost").unwrap();
+        let tree = repo
+            .find_tree(repo.treebuilder(None).unwrap().write().unwrap())
+            .unwrap();
+        let commit = repo
+            .commit(None, &signature, &signature, "Initial commit", &tree, &[])
+            .unwrap();
+
+        let urn = Urn::new(commit.into());
+        let namespace = format!("refs/namespaces/{}", urn.encode_id());
+        let main = format!("{}/refs/remotes/peer/heads/main", namespace);
+        let master = format!("{}/refs/remotes/peer/heads/master", namespace);
+
+        repo.reference(&main, commit, true, "test").unwrap();
+        repo.reference_symbolic(&master, &main, true, "test")
+            .unwrap();
+
+        let post_receive = PostReceive {
+            urn,
+            delegates: Vec::new(),
+            paths,
+            key_fingerprint: String::new(),
+            updates: Vec::new(),
+            env: ReceivePackEnv::default(),
+        };
+        let head = post_receive
+            .set_head("refs/remotes/peer/heads/master", "master", &repo)
+            .unwrap();
+
+        assert_eq!(head, None);
+        assert!(repo.find_reference(&format!("{}/HEAD", namespace)).is_err());
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
@@ -23,8 +23,12 @@ use std::str::FromStr;
 
 use envconfig::Envconfig;
 use git2::{Oid, Repository};
+use librad::git::identities::{Person, Project, SomeIdentity};
+use librad::git::Urn;
+use librad::identities::git::Identities;
 use librad::PeerId;
 
+use super::post_receive::RAD_ID_REF;
 use super::{
     types::{CertNonceStatus, CertStatus, ReceivePackEnv},
     CertSignerDetails,
@@ -101,6 +105,71 @@ impl PreReceive {
         pre_receive.verify_certificate()?;
         pre_receive.check_authorized_key()?;
         pre_receive.authorize_ref_updates()?;
+        pre_receive.verify_identity(&repo)?;
+
+        Ok(())
+    }
+
+    /// Make sure a newly pushed identity is valid, and belongs to the namespace it is pushed to.
+    fn verify_identity(&self, repo: &Repository) -> Result<(), Error> {
+        // The repository is namespaced, so the ref is relative to the project namespace.
+        let id_ref = format!("refs/{}", RAD_ID_REF);
+        // Updates to an existing identity are verified in `post-receive`.
+        if repo.find_reference(&id_ref).is_ok() {
+            return Ok(());
+        }
+        eprintln!("Verifying identity...");
+
+        let (_, from, to) = self
+            .updates
+            .iter()
+            .find(|(refname, _, _)| refname.ends_with(RAD_ID_REF))
+            .ok_or(Error::Unauthorized(
+                "identity ref 'rad/id' not found in updates",
+            ))?;
+        if !from.is_zero() || to.is_zero() {
+            return Err(Error::Unauthorized("identity ref 'rad/id' must be created"));
+        }
+        let urn = Urn::try_from_id(&self.env.git_namespace).map_err(|_| Error::InvalidId)?;
+
+        // Pushed objects are quarantined until the hook succeeds; opening the repository from
+        // the environment makes them visible to us.
+        let quarantine = Repository::open_from_env()?;
+        // Delegate identities are looked up in their own namespace, outside of the project's.
+        let monorepo = Repository::open_bare(&self.env.git_dir)?;
+        let lookup = |urn: Urn| {
+            monorepo.refname_to_id(&format!(
+                "refs/namespaces/{}/refs/{}",
+                urn.encode_id(),
+                RAD_ID_REF
+            ))
+        };
+
+        let identity = Identities::<SomeIdentity>::from(&quarantine)
+            .some_identity(*to)
+            .map_err(|_| Error::Unauthorized("identity ref 'rad/id' is not an identity"))?;
+        if identity.urn() != urn {
+            return Err(Error::Unauthorized(
+                "identity document doesn't match project id",
+            ));
+        }
+        let verified = match identity {
+            SomeIdentity::Person(_) => Identities::<Person>::from(&quarantine)
+                .verify(*to)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            SomeIdentity::Project(_) => Identities::<Project>::from(&quarantine)
+                .verify(*to, lookup)
+                .map(|_| ())
+                .map_err(|e| e.to_string()),
+            _ => return Err(Error::Unauthorized("unknown identity type")),
+        };
+        if let Err(err) = verified {
+            eprintln!("Identity verification failed: {}", err);
+
+            return Err(Error::Unauthorized("identity could not be verified"));
+        }
+        eprintln!("Identity {} verified.", urn);
 
         Ok(())
     }
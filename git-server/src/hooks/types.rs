@@ -154,6 +154,10 @@ pub struct ReceivePackEnv {
     #[envconfig(from = "RADICLE_RECEIVE_HOOK")]
     pub receive_hook: Option<PathBuf>,
 
+    /// path to unix socket notified of successful pushes.
+    #[envconfig(from = "RADICLE_RECEIVE_SOCKET")]
+    pub receive_socket: Option<PathBuf>,
+
     /// project default branch.
     #[envconfig(from = "RADICLE_DEFAULT_BRANCH")]
     pub default_branch: Option<String>,
@@ -1,6 +1,8 @@
 #![allow(clippy::type_complexity)]
 #![allow(clippy::too_many_arguments)]
+pub mod advertisement;
 pub mod error;
+pub mod rate_limit;
 
 #[cfg(feature = "hooks")]
 pub mod hooks;
@@ -9,30 +11,35 @@ use std::collections::{HashMap, HashSet};
 use std::convert::TryInto;
 use std::fs::File;
 use std::io::prelude::*;
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::{io, net};
 
 use anyhow::bail;
 use anyhow::Context as _;
-use axum::body::{BoxBody, Bytes};
-use axum::extract::{ConnectInfo, Path as AxumPath, RawQuery};
+use axum::body::{BoxBody, Bytes, HttpBody as _};
+use axum::extract::{ConnectInfo, Path as AxumPath, RawBody, RawQuery};
 use axum::http::{Method, StatusCode};
 use axum::response::IntoResponse;
-use axum::routing::any;
-use axum::{Extension, Router};
+use axum::routing::{any, get};
+use axum::{Extension, Json, Router};
 use axum_server::tls_rustls::RustlsConfig;
 use either::Either;
 use flate2::write::GzDecoder;
 use http::header::HeaderName;
-use http::HeaderMap;
-use hyper::body::Buf;
+use http::{Extensions, HeaderMap, Version};
 use hyper::http::{Request, Response};
 use hyper::Body;
-use tokio::sync::RwLock;
+use tokio::io::{AsyncBufReadExt as _, AsyncReadExt as _, AsyncWriteExt as _, BufReader};
+use tokio::process::{Child, ChildStdin, ChildStdout};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio::task::JoinHandle;
+use tower_http::compression::predicate::{Predicate as _, SizeAbove};
+use tower_http::compression::CompressionLayer;
 use tower_http::trace::TraceLayer;
 use tracing::Span;
 
@@ -43,13 +50,35 @@ use librad::identities::SomeIdentity;
 use librad::paths::Paths;
 use librad::profile::LnkHome;
 use librad::PeerId;
+use serde_json::{json, Value};
 
+use advertisement::AdvertisementCache;
 use error::Error;
+use rate_limit::RateLimiter;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Default number of storage objects kept in the pool, ie. the number of concurrent storage
+/// accesses. Each object holds open file descriptors to the monorepo.
 pub const STORAGE_POOL_SIZE: usize = 3;
 pub const AUTHORIZED_KEYS_FILE: &str = "authorized-keys";
+/// Directory under the git root holding per-namespace files, eg.
+/// `namespaces/<namespace>/authorized-keys`.
+pub const NAMESPACES_DIR: &str = "namespaces";
 pub const POST_RECEIVE_OK_HOOK: &str = "post-receive-ok";
+/// Size of the chunks streamed from the git backend to the client.
+pub const BACKEND_CHUNK_SIZE: usize = 64 * 1024;
+/// Size of the pieces in which gzip-encoded request bodies are decompressed. Since deflate
+/// compresses by at most about 1000:1, this bounds the decompressed data buffered at a time.
+pub const GZIP_INPUT_SIZE: usize = 1024;
+/// How long a request waits for a git backend slot when the maximum number of concurrent
+/// backends is reached, before giving up.
+pub const BACKEND_QUEUE_TIMEOUT: Duration = Duration::from_secs(30);
+/// Content types of the responses compressed for clients that accept it. Ref advertisements
+/// are text and compress well, while pack data is already deflated and isn't worth the CPU.
+pub const COMPRESSED_CONTENT_TYPES: &[&str] = &[
+    "application/x-git-upload-pack-advertisement",
+    "application/x-git-receive-pack-advertisement",
+];
 
 #[derive(Debug, Clone)]
 pub struct Options {
@@ -61,6 +90,17 @@ pub struct Options {
     pub git_receive_pack: bool,
     pub cert_nonce_seed: Option<String>,
     pub allow_unauthorized_keys: bool,
+    pub receive_pack_rate_limit: Option<u32>,
+    pub receive_pack_burst: Option<u32>,
+    pub max_push_bytes: Option<u64>,
+    pub receive_socket: Option<PathBuf>,
+    pub storage_pool_size: usize,
+    pub advertisement_cache_ttl: Option<Duration>,
+    pub allowed_urns: Option<Vec<Urn>>,
+    pub max_concurrent_backends: Option<usize>,
+    pub allow_partial_clone: bool,
+    pub disable_project_listing: bool,
+    pub trust_forwarded_for: bool,
 }
 
 #[derive(Clone)]
@@ -69,8 +109,19 @@ pub struct Context {
     root: LnkHome,
     git_receive_pack: bool,
     cert_nonce_seed: Option<String>,
+    allow_partial_clone: bool,
     git_receive_hook: PathBuf,
+    receive_socket: Option<PathBuf>,
     allow_unauthorized_keys: bool,
+    /// Whether to take the remote IP from proxy headers.
+    trust_forwarded_for: bool,
+    receive_pack_limiter: Option<RateLimiter>,
+    max_push_bytes: Option<u64>,
+    advertisements: Option<AdvertisementCache>,
+    /// Projects served by this instance, or `None` to serve all projects in storage.
+    allowed_urns: Option<HashSet<Urn>>,
+    /// Limits the number of git backend processes running at once, if set.
+    backends: Option<Arc<Semaphore>>,
     aliases: Arc<RwLock<HashMap<String, Urn>>>,
     pool: Pool<git::storage::ReadOnly>,
 }
@@ -81,7 +132,7 @@ impl Context {
         let paths = profile.paths();
         let pool = git::storage::Pool::new(
             git::storage::pool::ReadConfig::new(paths.clone()),
-            STORAGE_POOL_SIZE,
+            options.storage_pool_size,
         );
 
         let git_root = paths.git_dir().canonicalize()?;
@@ -94,27 +145,48 @@ impl Context {
             root,
             git_receive_pack: options.git_receive_pack,
             git_receive_hook,
+            receive_socket: options.receive_socket.clone(),
             cert_nonce_seed: options.cert_nonce_seed.clone(),
+            allow_partial_clone: options.allow_partial_clone,
             allow_unauthorized_keys: options.allow_unauthorized_keys,
+            trust_forwarded_for: options.trust_forwarded_for,
+            receive_pack_limiter: options
+                .receive_pack_rate_limit
+                .map(|rate| RateLimiter::new(rate, options.receive_pack_burst.unwrap_or(rate))),
+            max_push_bytes: options.max_push_bytes,
+            advertisements: options.advertisement_cache_ttl.map(AdvertisementCache::new),
+            allowed_urns: options
+                .allowed_urns
+                .as_ref()
+                .map(|urns| urns.iter().cloned().collect()),
+            backends: options
+                .max_concurrent_backends
+                .map(|max| Arc::new(Semaphore::new(max))),
             aliases: Default::default(),
             pool,
         })
     }
 
-    /// (Re-)load the authorized keys file.
-    pub fn load_authorized_keys(&self) -> io::Result<Vec<String>> {
-        let mut authorized_keys = HashSet::new();
+    /// (Re-)load the authorized keys of a namespace.
+    ///
+    /// If the namespace has its own authorized keys file, only those keys may push to it.
+    /// Otherwise, the global authorized keys file is used.
+    pub fn load_authorized_keys(&self, namespace: &str) -> io::Result<Vec<String>> {
+        let git_dir = self.paths.git_dir();
+        let project_keys = git_dir
+            .join(NAMESPACES_DIR)
+            .join(namespace)
+            .join(AUTHORIZED_KEYS_FILE);
+
+        if let Some(keys) = read_authorized_keys(&project_keys)? {
+            tracing::debug!("Loaded authorized keys for namespace {}", namespace);
+
+            return Ok(keys);
+        }
 
-        match File::open(self.paths.git_dir().join(AUTHORIZED_KEYS_FILE)) {
-            Ok(file) => {
-                for line in io::BufReader::new(file).lines() {
-                    let key = line?;
-                    if !key.is_empty() {
-                        authorized_keys.insert(key.clone());
-                    }
-                }
-            }
-            Err(err) if err.kind() == io::ErrorKind::NotFound => {
+        match read_authorized_keys(&git_dir.join(AUTHORIZED_KEYS_FILE)) {
+            Ok(Some(keys)) => return Ok(keys),
+            Ok(None) => {
                 if !self.allow_unauthorized_keys && self.git_receive_pack {
                     tracing::warn!("No authorized keys loaded");
                 }
@@ -123,7 +195,7 @@ impl Context {
                 tracing::error!("Authorized keys file could not be loaded: {}", err);
             }
         }
-        Ok(authorized_keys.into_iter().collect())
+        Ok(vec![])
     }
 
     /// Sets the config receive.advertisePushOptions, which lets the user known they can provide a push option `-o`,
@@ -162,6 +234,25 @@ impl Context {
         Ok(())
     }
 
+    /// Enables or disables partial clones, eg. `clone --filter=blob:none`.
+    ///
+    /// Besides accepting filters, objects omitted from a partial clone are fetched lazily by
+    /// id, which requires allowing `want` lines for objects that aren't ref tips. Only objects
+    /// reachable from the namespace's refs are allowed, since the object database is shared
+    /// between all projects.
+    pub fn set_partial_clone(&self) -> Result<(), Error> {
+        let value = if self.allow_partial_clone {
+            "true"
+        } else {
+            "false"
+        };
+
+        self.set_root_git_config("uploadpack.allowFilter", value)?;
+        self.set_root_git_config("uploadpack.allowReachableSHA1InWant", value)?;
+
+        Ok(())
+    }
+
     /// Enables users to submit a signed push: `push --signed`
     ///
     /// "You should set the certNonceSeed setting to some randomly generated long string that should
@@ -237,6 +328,40 @@ impl Context {
         Ok(())
     }
 
+    /// List the projects served by this instance, along with the alias they can be
+    /// fetched under, if any.
+    async fn projects(&self) -> Result<Vec<Value>, Error> {
+        use librad::git::identities::SomeIdentity::Project;
+
+        let storage = self.pool.get().await?;
+        let identities = identities::any::list(&storage)?;
+        // Aliases are resolved the same way as in `populate_aliases`: the first project
+        // with a given name gets the alias.
+        let mut names = HashSet::new();
+        let mut projects = Vec::new();
+
+        for identity in identities.flatten() {
+            if let Project(project) = identity {
+                let urn = project.urn();
+                let name = project.payload().subject.name.to_string();
+                let alias = names.insert(name.clone()).then(|| format!("{}.git", name));
+
+                if let Some(allowed) = &self.allowed_urns {
+                    if !allowed.contains(&urn) {
+                        continue;
+                    }
+                }
+                projects.push(json!({
+                    "urn": urn.to_string(),
+                    "name": name,
+                    "alias": alias,
+                }));
+            }
+        }
+
+        Ok(projects)
+    }
+
     async fn get_meta(
         &self,
         urn: &Urn,
@@ -317,53 +442,134 @@ pub async fn run(options: Options) -> anyhow::Result<()> {
     if let Err(e) = ctx.disable_gc() {
         bail!("Failed to disable gc: {:?}", e);
     }
+    if let Err(e) = ctx.set_partial_clone() {
+        bail!("Failed to set partial clone config: {:?}", e);
+    }
 
-    let app = Router::new()
-        .route("/:project_id/*request", any(git_handler))
+    let mut router = Router::new().route("/:project_id/*request", any(git_handler));
+    if !options.disable_project_listing {
+        router = router.route("/", get(projects_handler));
+    }
+
+    let app = router
         .layer(Extension(ctx.clone()))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|request: &Request<Body>| {
-                    tracing::info_span!(
+                    let span = tracing::info_span!(
                         "request",
                         method = %request.method(),
                         uri = %request.uri(),
+                        remote = tracing::field::Empty,
+                        urn = tracing::field::Empty,
                         status = tracing::field::Empty,
+                        bytes = tracing::field::Empty,
                         latency = tracing::field::Empty,
-                    )
+                    );
+                    if let Some(ConnectInfo(addr)) =
+                        request.extensions().get::<ConnectInfo<SocketAddr>>()
+                    {
+                        span.record("remote", &tracing::field::display(addr.ip()));
+                    }
+                    span
                 })
                 .on_response(
                     |response: &Response<BoxBody>, latency: Duration, span: &Span| {
                         span.record("status", &tracing::field::debug(response.status()));
                         span.record("latency", &tracing::field::debug(latency));
+                        // Only known up-front for buffered responses; streamed backend output
+                        // is accounted for when the backend exits.
+                        if let Some(bytes) = response.body().size_hint().exact() {
+                            span.record("bytes", &bytes);
+                        }
 
                         tracing::info!("Processed");
                     },
                 ),
         )
+        // Outside of the trace layer, so that uncompressed sizes are logged.
+        .layer(CompressionLayer::new().compress_when(SizeAbove::default().and(compressible)))
         .into_make_service_with_connect_info::<SocketAddr>();
 
+    // On shutdown, pushes in progress are allowed to complete, since interrupting the backend
+    // while it updates refs can leave storage in an inconsistent state.
     if let (Some(cert), Some(key)) = (options.tls_cert, options.tls_key) {
-        let config = RustlsConfig::from_pem_file(cert, key).await.unwrap();
+        let config = RustlsConfig::from_pem_file(&cert, &key).await.unwrap();
+
+        tokio::spawn(reload_tls_on_hangup(config.clone(), cert, key));
+
+        let handle = axum_server::Handle::new();
+
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                shared::shutdown_signal().await;
+                handle.graceful_shutdown(None);
+            }
+        });
 
         tracing::info!("listening on https://{}", options.listen);
         axum_server::bind_rustls(options.listen, config)
+            .handle(handle)
             .serve(app)
             .await?;
     } else {
         tracing::info!("listening on http://{}", options.listen);
-        axum::Server::bind(&options.listen).serve(app).await?;
+        axum::Server::bind(&options.listen)
+            .serve(app)
+            .with_graceful_shutdown(shared::shutdown_signal())
+            .await?;
     }
 
     Ok(())
 }
 
+/// Reload the TLS certificate and key on `SIGHUP`, keeping the current ones on failure.
+async fn reload_tls_on_hangup(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            tracing::error!("Failed to listen for SIGHUP, TLS reload disabled: {}", err);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => tracing::info!("TLS certificate reloaded from {:?}", cert),
+            Err(err) => tracing::error!(
+                "Failed to reload TLS certificate, keeping the current one: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Whether a response should be compressed, based on its content type.
+/// See [`COMPRESSED_CONTENT_TYPES`].
+fn compressible(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    headers
+        .get(http::header::CONTENT_TYPE)
+        .and_then(|h| h.to_str().ok())
+        .map_or(false, |content_type| {
+            COMPRESSED_CONTENT_TYPES.contains(&content_type)
+        })
+}
+
+/// List the projects served.
+/// `GET /`
+async fn projects_handler(Extension(ctx): Extension<Context>) -> impl IntoResponse {
+    let projects = ctx.projects().await?;
+
+    Ok::<_, Error>(Json(projects))
+}
+
 async fn git_handler(
     Extension(ctx): Extension<Context>,
     AxumPath((project_id, request)): AxumPath<(String, String)>,
     method: Method,
     headers: HeaderMap,
-    body: Bytes,
+    RawBody(body): RawBody,
     ConnectInfo(remote): ConnectInfo<SocketAddr>,
     query: RawQuery,
 ) -> impl IntoResponse {
@@ -390,6 +596,26 @@ async fn git_handler(
         Urn::try_from_id(project_id).map_err(|_| Error::InvalidId)?
     };
 
+    // Part of the access log, see the trace layer in `run`.
+    Span::current().record("urn", &tracing::field::display(&urn));
+
+    // Behind a reverse proxy, the TCP peer is the proxy itself.
+    let remote = match forwarded_ip(&headers).filter(|_| ctx.trust_forwarded_for) {
+        Some(ip) => {
+            Span::current().record("remote", &tracing::field::display(ip));
+            SocketAddr::new(ip, remote.port())
+        }
+        None => remote,
+    };
+
+    if let Some(allowed) = &ctx.allowed_urns {
+        if !allowed.contains(&urn) {
+            tracing::debug!("project {} is not served by this instance", urn);
+
+            return Err(Error::NamespaceNotFound);
+        }
+    }
+
     let (status, headers, body) = git(
         ctx, method, headers, body, remote, urn, peer_id, &request, query,
     )
@@ -403,20 +629,20 @@ async fn git_handler(
         }
     }
 
-    Ok::<_, Error>((status, response_headers, body))
+    Ok::<_, Error>((status, response_headers, axum::body::boxed(body)))
 }
 
 async fn git(
     ctx: Context,
     method: Method,
     headers: HeaderMap,
-    mut body: impl Buf,
+    mut body: Body,
     remote: net::SocketAddr,
     urn: Urn,
     peer_id: Option<PeerId>,
     path: &str,
     query: String,
-) -> Result<(http::StatusCode, HashMap<String, Vec<String>>, Vec<u8>), Error> {
+) -> Result<(http::StatusCode, HashMap<String, Vec<String>>, Body), Error> {
     let namespace = urn.encode_id();
     let content_type =
         if let Some(Ok(content_type)) = headers.get("Content-Type").map(|h| h.to_str()) {
@@ -424,17 +650,71 @@ async fn git(
         } else {
             ""
         };
+    // Eg. `version=2`, sent by clients that support newer versions of the git wire protocol.
+    // Without it, the backend falls back to protocol v0.
+    let protocol = headers
+        .get("Git-Protocol")
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or_default()
+        .to_owned();
+    let mut max_body_bytes = None;
+    let receive_pack = matches!(
+        (path, query.as_str()),
+        ("git-receive-pack", _) | (_, "service=git-receive-pack")
+    );
     let authorized_keys = match (path, query.as_str()) {
         // Eg. `git push`
         ("git-receive-pack", _) | (_, "service=git-receive-pack") => {
             if !ctx.git_receive_pack {
                 return Err(Error::ServiceUnavailable("git-receive-pack"));
             }
-            ctx.load_authorized_keys()?
+            // Only the push itself is charged, not the ref advertisement that precedes it.
+            let limiter = ctx
+                .receive_pack_limiter
+                .as_ref()
+                .filter(|_| path == "git-receive-pack");
+            if let Some(limiter) = limiter {
+                if !limiter.check(remote.ip()) {
+                    return Err(Error::RateLimited("git-receive-pack"));
+                }
+            }
+            max_body_bytes = ctx.max_push_bytes;
+            ctx.load_authorized_keys(&namespace)?
         }
         _ => vec![],
     };
 
+    // Eg. the first request of a `git clone` or `git fetch`.
+    let advertisements = ctx.advertisements.clone().filter(|_| {
+        method == Method::GET && path == "info/refs" && query == "service=git-upload-pack"
+    });
+    if let Some(ad) = advertisements
+        .as_ref()
+        .and_then(|c| c.get(&namespace, &protocol))
+    {
+        tracing::debug!("serving cached ref advertisement for {}", urn);
+
+        return Ok((StatusCode::OK, ad.headers, Body::from(ad.body)));
+    }
+
+    // Whether the request body is compressed.
+    let gzip = matches!(
+        headers.get("Content-Encoding").map(|h| h.to_str()),
+        Some(Ok("gzip"))
+    );
+    // Uncompressed bodies that are too large are rejected up-front. Otherwise, the limit
+    // applies to the decompressed size, and is enforced as the body is copied to the backend.
+    if let Some(max) = max_body_bytes {
+        let length = headers
+            .get(http::header::CONTENT_LENGTH)
+            .and_then(|h| h.to_str().ok())
+            .and_then(|h| h.parse::<u64>().ok());
+
+        if matches!(length, Some(length) if !gzip && length > max) {
+            return Err(Error::PayloadTooLarge(max));
+        }
+    }
+
     let (name, delegates, default_branch) = ctx.get_meta(&urn).await?;
 
     tracing::debug!("headers: {:?}", headers);
@@ -479,11 +759,17 @@ async fn git(
     }
 
     cmd.env("RADICLE_RECEIVE_HOOK", &ctx.git_receive_hook);
+    if let Some(socket) = &ctx.receive_socket {
+        cmd.env("RADICLE_RECEIVE_SOCKET", socket);
+    }
     cmd.env("REQUEST_METHOD", method.as_str());
     cmd.env("GIT_PROJECT_ROOT", ctx.paths.git_dir().canonicalize()?);
-    cmd.env("GIT_NAMESPACE", namespace);
+    cmd.env("GIT_NAMESPACE", &namespace);
     cmd.env("PATH_INFO", Path::new("/").join(path));
     cmd.env("CONTENT_TYPE", content_type);
+    if !protocol.is_empty() {
+        cmd.env("GIT_PROTOCOL", &protocol);
+    }
     // "The backend process sets GIT_COMMITTER_NAME to $REMOTE_USER and GIT_COMMITTER_EMAIL to
     // ${REMOTE_USER}@http.${REMOTE_ADDR}, ensuring that any reflogs created by git-receive-pack
     // contain some identifying information of the remote user who performed the push."
@@ -498,98 +784,334 @@ async fn git(
         .stdout(Stdio::piped())
         .stdin(Stdio::piped());
 
+    // Wait for a backend slot, so that bursts of requests queue up rather than spawning
+    // an unbounded number of processes.
+    let permit = if let Some(backends) = &ctx.backends {
+        Some(acquire_backend(backends.clone()).await?)
+    } else {
+        None
+    };
+
     // Spawn the git backend.
-    let mut child = cmd.spawn()?;
+    let backend = BackendRequest {
+        urn: urn.clone(),
+        method: method.clone(),
+        path: path.to_owned(),
+        started: Instant::now(),
+    };
+    let mut child = tokio::process::Command::from(cmd).spawn()?;
 
-    // Whether the request body is compressed.
-    let gzip = matches!(
-        headers.get("Content-Encoding").map(|h| h.to_str()),
-        Some(Ok("gzip"))
-    );
+    // Drain stderr concurrently, so that the backend never blocks on a full pipe.
+    // These are safe because we captured the child's stdin, stdout and stderr.
+    let mut stderr = child.stderr.take().unwrap();
+    let stderr = tokio::spawn(async move {
+        let mut output = Vec::new();
+        stderr.read_to_end(&mut output).await.map(|_| output)
+    });
 
     {
-        // This is safe because we captured the child's stdin.
         let mut stdin = child.stdin.take().unwrap();
 
-        // Copy the request body to git-http-backend's stdin.
-        if gzip {
-            let mut decoder = GzDecoder::new(&mut stdin);
-            let mut reader = body.reader();
+        // Copy the request body to git-http-backend's stdin, as it is received.
+        if let Err(err) = copy_body(&mut body, &mut stdin, gzip, max_body_bytes).await {
+            // Don't leave the backend running, or a zombie behind.
+            child.kill().await.ok();
+
+            return Err(err);
+        }
+    }
 
-            io::copy(&mut reader, &mut decoder)?;
-            decoder.finish()?;
+    let mut reader = BufReader::new(child.stdout.take().unwrap());
+    let mut headers = HashMap::new();
+    let mut line = String::new();
+
+    // Parse headers returned by git so that we can use them in the client response.
+    // Everything after the blank line is the response body, which is streamed to the client.
+    loop {
+        line.clear();
+
+        if reader.read_line(&mut line).await? == 0 {
+            // The backend closed its output before the end of the headers: this only
+            // happens when something went wrong.
+            wait_backend(child, stderr, &backend, Error::Backend.status(), 0).await?;
+
+            return Err(Error::Backend);
+        }
+        let line = line.trim_end_matches(&['\r', '\n'][..]);
+
+        if line.is_empty() {
+            break;
+        }
+
+        let mut parts = line.splitn(2, ':');
+        let key = parts.next();
+        let value = parts.next();
+
+        if let (Some(key), Some(value)) = (key, value) {
+            let value = &value[1..];
+
+            headers
+                .entry(key.to_string())
+                .or_insert_with(Vec::new)
+                .push(value.to_string());
         } else {
-            while body.has_remaining() {
-                let mut chunk = body.chunk();
-                let count = chunk.len();
+            // The backend is still running, so it has to be stopped before it can be reaped.
+            child.start_kill().ok();
+            wait_backend(child, stderr, &backend, Error::Backend.status(), 0).await?;
 
-                io::copy(&mut chunk, &mut stdin)?;
-                body.advance(count);
-            }
+            return Err(Error::Backend);
         }
     }
 
-    match child.wait_with_output() {
-        Ok(output) if output.status.success() => {
-            tracing::info!("git-http-backend: exited successfully for {}", urn);
+    let status = {
+        tracing::debug!("http-backend: {:?}", &headers);
 
-            let mut reader = std::io::Cursor::new(output.stdout);
-            let mut headers = HashMap::new();
+        let line = headers.remove("Status").unwrap_or_default();
+        let line = line.into_iter().next().unwrap_or_default();
+        let mut parts = line.split(' ');
 
-            // Parse headers returned by git so that we can use them in the client response.
-            for line in io::Read::by_ref(&mut reader).lines() {
-                let line = line?;
+        parts
+            .next()
+            .and_then(|p| p.parse().ok())
+            .unwrap_or(StatusCode::OK)
+    };
+
+    let (mut sender, body) = Body::channel();
+    let pushed = receive_pack.then(|| ctx.advertisements.clone()).flatten();
+
+    tokio::spawn(async move {
+        // Released once the backend has exited.
+        let _permit = permit;
+        let result = stream_backend(child, reader, &mut sender, stderr, &backend, status).await;
+
+        // Refs may have been updated even if the backend failed.
+        if let Some(advertisements) = pushed {
+            advertisements.invalidate(&backend.urn.encode_id());
+        }
+        if result.is_err() {
+            // Let the client know that the response is incomplete.
+            sender.abort();
+        }
+    });
+
+    if let Some(advertisements) = advertisements {
+        if status == StatusCode::OK {
+            // Advertisements are small, so they are buffered rather than streamed.
+            let body = hyper::body::to_bytes(body)
+                .await
+                .map_err(|_| Error::Backend)?;
+            advertisements.insert(&namespace, &protocol, headers.clone(), body.clone());
+
+            return Ok((status, headers, Body::from(body)));
+        }
+    }
 
-                if line.is_empty() || line == "\r" {
+    Ok((status, headers, body))
+}
+
+/// Stream the git backend's output to the client, and wait for the backend to exit.
+///
+/// If the client goes away or the output can't be read, the backend is killed, so that it
+/// doesn't block forever on a full pipe, holding on to its backend slot.
+async fn stream_backend(
+    mut child: Child,
+    mut reader: BufReader<ChildStdout>,
+    sender: &mut hyper::body::Sender,
+    stderr: JoinHandle<io::Result<Vec<u8>>>,
+    backend: &BackendRequest,
+    status: StatusCode,
+) -> Result<(), Error> {
+    let mut buffer = vec![0; BACKEND_CHUNK_SIZE];
+    let mut bytes = 0;
+    let mut failed = false;
+
+    loop {
+        match reader.read(&mut buffer).await {
+            Ok(0) => break,
+            Ok(n) => {
+                bytes += n;
+
+                if sender
+                    .send_data(Bytes::copy_from_slice(&buffer[..n]))
+                    .await
+                    .is_err()
+                {
+                    tracing::debug!("git-http-backend: client went away for {}", backend.urn);
+                    failed = true;
                     break;
                 }
+            }
+            Err(err) => {
+                tracing::error!("git-http-backend: failed to read output: {}", err);
+                failed = true;
+                break;
+            }
+        }
+    }
 
-                let mut parts = line.splitn(2, ':');
-                let key = parts.next();
-                let value = parts.next();
+    if failed {
+        drop(reader);
+        child.start_kill().ok();
+    }
+    wait_backend(child, stderr, backend, status, bytes).await?;
 
-                if let (Some(key), Some(value)) = (key, value) {
-                    let value = &value[1..];
+    if failed {
+        return Err(Error::Backend);
+    }
+    Ok(())
+}
 
-                    headers
-                        .entry(key.to_string())
-                        .or_insert_with(Vec::new)
-                        .push(value.to_string());
-                } else {
-                    return Err(Error::Backend);
-                }
+/// Copy a request body to the git backend as it is received, decompressing it if it is
+/// gzip-encoded. Fails once more than `max` bytes were written, if set.
+async fn copy_body(
+    body: &mut Body,
+    stdin: &mut ChildStdin,
+    gzip: bool,
+    max: Option<u64>,
+) -> Result<(), Error> {
+    let mut decoder = gzip.then(|| GzDecoder::new(Vec::new()));
+    let mut written = 0;
+
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+
+        if let Some(decoder) = &mut decoder {
+            // Compressed data is decoded in small pieces, so that only a bounded amount of
+            // decompressed data is held in memory at a time, however well it compresses.
+            for piece in chunk.chunks(GZIP_INPUT_SIZE) {
+                decoder.write_all(piece)?;
+
+                let output = std::mem::take(decoder.get_mut());
+                write_body(stdin, &output, &mut written, max).await?;
             }
+        } else {
+            write_body(stdin, &chunk, &mut written, max).await?;
+        }
+    }
+    if let Some(decoder) = decoder {
+        let output = decoder.finish()?;
+        write_body(stdin, &output, &mut written, max).await?;
+    }
+    Ok(())
+}
 
-            let status = {
-                tracing::debug!("http-backend: {:?}", &headers);
+/// Write part of a request body to the git backend, keeping count of the bytes written.
+async fn write_body(
+    stdin: &mut ChildStdin,
+    data: &[u8],
+    written: &mut u64,
+    max: Option<u64>,
+) -> Result<(), Error> {
+    *written += data.len() as u64;
+
+    if let Some(max) = max {
+        if *written > max {
+            return Err(Error::PayloadTooLarge(max));
+        }
+    }
+    stdin.write_all(data).await?;
 
-                let line = headers.remove("Status").unwrap_or_default();
-                let line = line.into_iter().next().unwrap_or_default();
-                let mut parts = line.split(' ');
+    Ok(())
+}
 
-                parts
-                    .next()
-                    .and_then(|p| p.parse().ok())
-                    .unwrap_or(StatusCode::OK)
-            };
+/// Acquire a git backend slot, waiting at most [`BACKEND_QUEUE_TIMEOUT`] for one to free up.
+async fn acquire_backend(backends: Arc<Semaphore>) -> Result<OwnedSemaphorePermit, Error> {
+    match tokio::time::timeout(BACKEND_QUEUE_TIMEOUT, backends.acquire_owned()).await {
+        Ok(Ok(permit)) => Ok(permit),
+        // The semaphore is never closed.
+        Ok(Err(_)) | Err(_) => Err(Error::Busy),
+    }
+}
 
-            let position = reader.position() as usize;
-            let body = reader.into_inner().split_off(position);
+/// A request served by the git backend, for logging.
+struct BackendRequest {
+    urn: Urn,
+    method: Method,
+    path: String,
+    /// When the backend was spawned.
+    started: Instant,
+}
 
-            Ok((status, headers, body))
-        }
-        Ok(output) => {
-            tracing::error!("git-http-backend: exited with code {}", output.status);
+/// Wait for the git backend to exit, and log its status along with the response status,
+/// the number of response body bytes it produced and how long it ran for.
+async fn wait_backend(
+    mut child: Child,
+    stderr: JoinHandle<io::Result<Vec<u8>>>,
+    request: &BackendRequest,
+    status: StatusCode,
+    bytes: usize,
+) -> Result<(), Error> {
+    let exit = child.wait().await?;
+    let elapsed = request.started.elapsed();
+
+    if exit.success() {
+        tracing::info!(
+            urn = %request.urn,
+            method = %request.method,
+            path = %request.path,
+            status = status.as_u16(),
+            bytes,
+            elapsed = ?elapsed,
+            "git-http-backend: exited successfully"
+        );
 
-            if let Ok(output) = std::str::from_utf8(&output.stderr) {
-                tracing::error!("git-http-backend: stderr: {}", output.trim_end());
-            }
-            Err(Error::Backend)
+        return Ok(());
+    }
+    tracing::error!(
+        urn = %request.urn,
+        method = %request.method,
+        path = %request.path,
+        status = status.as_u16(),
+        bytes,
+        elapsed = ?elapsed,
+        "git-http-backend: exited with code {}",
+        exit
+    );
+
+    if let Ok(Ok(output)) = stderr.await {
+        if let Ok(output) = std::str::from_utf8(&output) {
+            tracing::error!("git-http-backend: stderr: {}", output.trim_end());
         }
-        Err(err) => {
-            panic!("failed to wait for git-http-backend: {}", err);
+    }
+    Err(Error::Backend)
+}
+
+/// Get the client IP set by a reverse proxy, from the `X-Real-IP` header, or else the last
+/// address of the `X-Forwarded-For` header, ie. the one added by the nearest proxy.
+fn forwarded_ip(headers: &HeaderMap) -> Option<IpAddr> {
+    let real_ip = headers
+        .get("X-Real-IP")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|ip| ip.trim().parse().ok());
+
+    real_ip.or_else(|| {
+        headers
+            .get_all("X-Forwarded-For")
+            .iter()
+            .filter_map(|h| h.to_str().ok())
+            .flat_map(|h| h.split(','))
+            .last()
+            .and_then(|ip| ip.trim().parse().ok())
+    })
+}
+
+/// Read an authorized keys file, with one key per line.
+/// Returns `None` if the file doesn't exist.
+fn read_authorized_keys(path: &Path) -> io::Result<Option<Vec<String>>> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut authorized_keys = HashSet::new();
+
+    for line in io::BufReader::new(file).lines() {
+        let key = line?;
+        if !key.is_empty() {
+            authorized_keys.insert(key);
         }
     }
+    Ok(Some(authorized_keys.into_iter().collect()))
 }
 
 /// Helper method to generate random string for cert nonce;
@@ -619,3 +1141,45 @@ fn parse_ref(input: &str) -> Result<(PeerId, String), io::Error> {
 
     Ok((peer_id, rest.to_owned()))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_stream_backend_client_gone() {
+        // Far more output than fits in a pipe, like a large clone.
+        let mut child = tokio::process::Command::new("sh")
+            .args(["-c", "head -c 67108864 /dev/zero"])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .unwrap();
+        let reader = BufReader::new(child.stdout.take().unwrap());
+        let mut stderr = child.stderr.take().unwrap();
+        let stderr = tokio::spawn(async move {
+            let mut output = Vec::new();
+            stderr.read_to_end(&mut output).await.map(|_| output)
+        });
+        let backend = BackendRequest {
+            urn: Urn::try_from_id("hnrkfbrd7y9674d8ow8uioki16fniwcyoz67y").unwrap(),
+            method: Method::POST,
+            path: String::from("git-upload-pack"),
+            started: Instant::now(),
+        };
+        let (mut sender, mut body) = Body::channel();
+        let stream = tokio::spawn(async move {
+            stream_backend(child, reader, &mut sender, stderr, &backend, StatusCode::OK).await
+        });
+
+        // The client reads part of the response, then disconnects.
+        body.data().await.unwrap().unwrap();
+        drop(body);
+
+        let result = tokio::time::timeout(Duration::from_secs(10), stream)
+            .await
+            .expect("the backend is stopped once the client is gone")
+            .unwrap();
+        assert!(result.is_err());
+    }
+}
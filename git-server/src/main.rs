@@ -1,6 +1,8 @@
 use std::path::PathBuf;
+use std::time::Duration;
 use std::{net, process};
 
+use librad::git::Urn;
 use radicle_git_server as server;
 
 use argh::FromArgs;
@@ -39,6 +41,59 @@ pub struct Options {
     /// allow unauthorized keys, ignores gpg certificate verification
     #[argh(switch)]
     pub allow_unauthorized_keys: bool,
+
+    /// maximum number of 'git-receive-pack' requests per minute, per remote IP (default: unlimited)
+    #[argh(option)]
+    pub receive_pack_rate_limit: Option<u32>,
+
+    /// maximum burst of 'git-receive-pack' requests, per remote IP (default: the rate limit)
+    #[argh(option)]
+    pub receive_pack_burst: Option<u32>,
+
+    /// maximum size in bytes of a 'git-receive-pack' request body, after decompression (default: unlimited)
+    #[argh(option)]
+    pub max_push_bytes: Option<u64>,
+
+    /// unix socket to notify with a JSON event after each successful push
+    #[argh(option)]
+    pub receive_socket: Option<PathBuf>,
+
+    /// number of concurrent storage accesses; larger pools serve more requests in parallel
+    /// at the cost of more open file descriptors (default: 3)
+    #[argh(option, default = "server::STORAGE_POOL_SIZE")]
+    pub storage_pool_size: usize,
+
+    /// cache 'git-upload-pack' ref advertisements in memory for this many seconds, or until
+    /// the next push (default: disabled)
+    #[argh(option)]
+    pub advertisement_cache_ttl: Option<u64>,
+
+    /// only serve the given project; may be specified multiple times (default: serve all
+    /// projects in storage)
+    #[argh(option, long = "allow-urn")]
+    pub allowed_urns: Vec<Urn>,
+
+    /// maximum number of git backend processes running at once; further requests wait for
+    /// a slot, or fail after 30 seconds (default: unlimited)
+    #[argh(option)]
+    pub max_concurrent_backends: Option<usize>,
+
+    /// allow partial clones, eg. `git clone --filter=blob:none` (default: false)
+    #[argh(switch)]
+    pub allow_partial_clone: bool,
+
+    /// don't list the projects served at `/`
+    #[argh(switch)]
+    pub disable_project_listing: bool,
+
+    /// take the remote IP from the `X-Real-IP` or `X-Forwarded-For` headers; only enable
+    /// this behind a reverse proxy that sets them
+    #[argh(switch)]
+    pub trust_forwarded_for: bool,
+
+    /// log output format, either `plain` or `gcp` (default: plain)
+    #[argh(option, default = "shared::LogFmt::default()")]
+    pub log_format: shared::LogFmt,
 }
 
 impl Options {
@@ -58,6 +113,21 @@ impl From<Options> for server::Options {
             git_receive_pack: other.git_receive_pack,
             cert_nonce_seed: other.cert_nonce_seed,
             allow_unauthorized_keys: other.allow_unauthorized_keys,
+            receive_pack_rate_limit: other.receive_pack_rate_limit,
+            receive_pack_burst: other.receive_pack_burst,
+            max_push_bytes: other.max_push_bytes,
+            receive_socket: other.receive_socket,
+            storage_pool_size: other.storage_pool_size,
+            advertisement_cache_ttl: other.advertisement_cache_ttl.map(Duration::from_secs),
+            allowed_urns: if other.allowed_urns.is_empty() {
+                None
+            } else {
+                Some(other.allowed_urns)
+            },
+            max_concurrent_backends: other.max_concurrent_backends,
+            allow_partial_clone: other.allow_partial_clone,
+            disable_project_listing: other.disable_project_listing,
+            trust_forwarded_for: other.trust_forwarded_for,
         }
     }
 }
@@ -66,7 +136,7 @@ impl From<Options> for server::Options {
 async fn main() {
     let options = Options::from_env();
 
-    shared::init_logger();
+    shared::init_logger(options.log_format);
     tracing::info!("version {}-{}", env!("CARGO_PKG_VERSION"), env!("GIT_HEAD"));
 
     match server::run(options.into()).await {
@@ -0,0 +1,133 @@
+//! Per-IP request rate limiting.
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Number of tracked addresses above which idle buckets are pruned.
+const MAX_BUCKETS: usize = 4096;
+
+/// A token-bucket rate limiter, keyed on the remote IP address.
+///
+/// Each address starts with `burst` tokens, and regains tokens at a constant
+/// rate, up to `burst`. Every request consumes one token.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Tokens regained per second.
+    rate: f64,
+    /// Maximum number of tokens a bucket can hold.
+    burst: f64,
+    buckets: Arc<Mutex<HashMap<IpAddr, Bucket>>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    tokens: f64,
+    refreshed: Instant,
+}
+
+impl RateLimiter {
+    /// Create a new rate limiter allowing `per_minute` requests per minute, with bursts
+    /// of up to `burst` requests.
+    pub fn new(per_minute: u32, burst: u32) -> Self {
+        Self {
+            rate: f64::from(per_minute) / 60.,
+            burst: f64::from(burst.max(1)),
+            buckets: Default::default(),
+        }
+    }
+
+    /// Check whether a request from the given address is allowed, consuming a token if so.
+    pub fn check(&self, addr: IpAddr) -> bool {
+        self.check_at(addr, Instant::now())
+    }
+
+    fn check_at(&self, addr: IpAddr, now: Instant) -> bool {
+        let mut buckets = self
+            .buckets
+            .lock()
+            .expect("rate limiter lock is not poisoned");
+
+        if buckets.len() >= MAX_BUCKETS {
+            // Buckets that would be full by now are equivalent to new ones.
+            let (rate, burst) = (self.rate, self.burst);
+            buckets.retain(|_, b| b.refill(now, rate, burst) < burst);
+        }
+
+        let bucket = buckets.entry(addr).or_insert(Bucket {
+            tokens: self.burst,
+            refreshed: now,
+        });
+        bucket.tokens = bucket.refill(now, self.rate, self.burst);
+        bucket.refreshed = now;
+
+        if bucket.tokens >= 1. {
+            bucket.tokens -= 1.;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+impl Bucket {
+    /// Number of tokens in the bucket at the given time.
+    fn refill(&self, now: Instant, rate: f64, burst: f64) -> f64 {
+        let elapsed = now.duration_since(self.refreshed).as_secs_f64();
+
+        (self.tokens + elapsed * rate).min(burst)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    const ALICE: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+    const BOB: IpAddr = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 2));
+
+    #[test]
+    fn test_burst() {
+        let limiter = RateLimiter::new(60, 3);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ALICE, now));
+        assert!(limiter.check_at(ALICE, now));
+        assert!(limiter.check_at(ALICE, now));
+        assert!(!limiter.check_at(ALICE, now));
+    }
+
+    #[test]
+    fn test_refill() {
+        let limiter = RateLimiter::new(60, 2);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ALICE, now));
+        assert!(limiter.check_at(ALICE, now));
+        assert!(!limiter.check_at(ALICE, now));
+
+        // One token is regained every second.
+        assert!(!limiter.check_at(ALICE, now + Duration::from_millis(500)));
+        assert!(limiter.check_at(ALICE, now + Duration::from_secs(1)));
+        assert!(!limiter.check_at(ALICE, now + Duration::from_secs(1)));
+
+        // Tokens don't accumulate beyond the burst.
+        let later = now + Duration::from_secs(60);
+        assert!(limiter.check_at(ALICE, later));
+        assert!(limiter.check_at(ALICE, later));
+        assert!(!limiter.check_at(ALICE, later));
+    }
+
+    #[test]
+    fn test_per_ip() {
+        let limiter = RateLimiter::new(60, 1);
+        let now = Instant::now();
+
+        assert!(limiter.check_at(ALICE, now));
+        assert!(!limiter.check_at(ALICE, now));
+        assert!(limiter.check_at(BOB, now));
+        assert!(!limiter.check_at(BOB, now));
+    }
+}
@@ -2,7 +2,8 @@ use axum::extract::path::ErrorKind;
 use axum::extract::rejection::{PathRejection, QueryRejection};
 use axum::extract::{FromRequest, RequestParts};
 use axum::http::StatusCode;
-use axum::{async_trait, Json};
+use axum::response::{IntoResponse, Response};
+use axum::{async_trait, BoxError};
 
 use serde::de::DeserializeOwned;
 use serde::Serialize;
@@ -26,17 +27,17 @@ where
                     PathRejection::FailedToDeserializePathParams(inner) => {
                         let kind = inner.into_kind();
                         match &kind {
-                            ErrorKind::Message(msg) => Json(Error {
+                            ErrorKind::Message(msg) => axum::Json(Error {
                                 success: false,
                                 error: msg.to_string(),
                             }),
-                            _ => Json(Error {
+                            _ => axum::Json(Error {
                                 success: false,
                                 error: kind.to_string(),
                             }),
                         }
                     }
-                    _ => Json(Error {
+                    _ => axum::Json(Error {
                         success: false,
                         error: format!("{}", rejection),
                     }),
@@ -65,11 +66,11 @@ where
             Err(rejection) => {
                 let status = StatusCode::BAD_REQUEST;
                 let body = match rejection {
-                    QueryRejection::FailedToDeserializeQueryString(inner) => Json(Error {
+                    QueryRejection::FailedToDeserializeQueryString(inner) => axum::Json(Error {
                         success: false,
                         error: inner.to_string(),
                     }),
-                    _ => Json(Error {
+                    _ => axum::Json(Error {
                         success: false,
                         error: format!("{}", rejection),
                     }),
@@ -81,6 +82,50 @@ where
     }
 }
 
+pub struct Json<T>(pub T);
+
+#[async_trait]
+impl<B, T> FromRequest<B> for Json<T>
+where
+    T: DeserializeOwned + Send,
+    B: axum::body::HttpBody + Send,
+    B::Data: Send,
+    B::Error: Into<BoxError>,
+{
+    type Rejection = Response;
+
+    async fn from_request(req: &mut RequestParts<B>) -> Result<Self, Self::Rejection> {
+        match axum::Json::<T>::from_request(req).await {
+            Ok(value) => Ok(Self(value.0)),
+            // Bodies without a `Content-Length` are only found to be too large as they are
+            // read, which axum otherwise reports as a failure to buffer the body.
+            Err(rejection) if is_length_limit(&rejection) => Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                axum::Json(Error {
+                    success: false,
+                    error: format!("{}", rejection),
+                }),
+            )
+                .into_response()),
+            Err(rejection) => Err(rejection.into_response()),
+        }
+    }
+}
+
+/// Whether an error was caused by a body exceeding its size limit, eg. the one set by
+/// `RequestBodyLimitLayer`.
+fn is_length_limit(err: &(dyn std::error::Error + 'static)) -> bool {
+    let mut source = Some(err);
+
+    while let Some(err) = source {
+        if err.is::<http_body::LengthLimitError>() {
+            return true;
+        }
+        source = err.source();
+    }
+    false
+}
+
 #[derive(Serialize)]
 pub struct Error {
     success: bool,
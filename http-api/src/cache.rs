@@ -0,0 +1,136 @@
+//! In-memory cache of expensive, immutable responses.
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use librad::git::Urn;
+
+/// A bounded least-recently-used cache, keyed on project and an arbitrary key, eg. a commit SHA.
+///
+/// Entries for a commit never go stale, unless a branch is force-pushed; in that case all
+/// entries of the project should be purged. A cache with a capacity of zero is disabled.
+pub struct Cache<T> {
+    capacity: usize,
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+struct Inner<T> {
+    /// Cached values, along with the last time they were used.
+    entries: HashMap<(Urn, String), (u64, Arc<T>)>,
+    /// Logical clock, incremented on every access.
+    clock: u64,
+}
+
+impl<T> Clone for Cache<T> {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<T> Cache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            inner: Arc::new(Mutex::new(Inner {
+                entries: HashMap::new(),
+                clock: 0,
+            })),
+        }
+    }
+
+    pub fn get(&self, urn: &Urn, key: &str) -> Option<Arc<T>> {
+        if self.capacity == 0 {
+            return None;
+        }
+        let mut inner = self.inner.lock().expect("Cache::get: lock is not poisoned");
+        let Inner { entries, clock } = &mut *inner;
+
+        *clock += 1;
+        entries
+            .get_mut(&(urn.clone(), key.to_owned()))
+            .map(|(used, value)| {
+                *used = *clock;
+                value.clone()
+            })
+    }
+
+    pub fn insert(&self, urn: &Urn, key: &str, value: Arc<T>) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Cache::insert: lock is not poisoned");
+        let Inner { entries, clock } = &mut *inner;
+        let key = (urn.clone(), key.to_owned());
+
+        if !entries.contains_key(&key) && entries.len() >= self.capacity {
+            // Evict the least recently used entry.
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, (used, _))| *used)
+                .map(|(k, _)| k.clone());
+
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        *clock += 1;
+        entries.insert(key, (*clock, value));
+    }
+
+    /// Remove all entries of a project, returning the number of entries removed.
+    pub fn purge(&self, urn: &Urn) -> usize {
+        let mut inner = self
+            .inner
+            .lock()
+            .expect("Cache::purge: lock is not poisoned");
+        let before = inner.entries.len();
+
+        inner.entries.retain(|(u, _), _| u != urn);
+        before - inner.entries.len()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn urn(name: &str) -> Urn {
+        let oid = git2::Oid::hash_object(git2::ObjectType::Blob, name.as_bytes()).unwrap();
+        Urn::new(oid.into())
+    }
+
+    #[test]
+    fn test_cache_eviction_and_purge() {
+        let a = urn("a");
+        let b = urn("b");
+        let cache = Cache::new(2);
+
+        cache.insert(&a, "1", Arc::new(1));
+        cache.insert(&a, "2", Arc::new(2));
+        assert_eq!(cache.get(&a, "1").as_deref(), Some(&1));
+
+        // "2" is now the least recently used entry.
+        cache.insert(&b, "3", Arc::new(3));
+        assert_eq!(cache.get(&a, "2"), None);
+        assert_eq!(cache.get(&a, "1").as_deref(), Some(&1));
+        assert_eq!(cache.get(&b, "3").as_deref(), Some(&3));
+
+        assert_eq!(cache.purge(&a), 1);
+        assert_eq!(cache.get(&a, "1"), None);
+        assert_eq!(cache.get(&b, "3").as_deref(), Some(&3));
+    }
+
+    #[test]
+    fn test_cache_disabled() {
+        let urn = urn("a");
+        let cache = Cache::new(0);
+
+        cache.insert(&urn, "1", Arc::new(1));
+        assert_eq!(cache.get(&urn, "1"), None);
+    }
+}
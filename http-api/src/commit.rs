@@ -11,13 +11,19 @@ pub struct CommitsQueryString {
     pub until: Option<i64>,
     pub page: Option<usize>,
     pub per_page: Option<usize>,
+    pub max: Option<usize>,
+    pub author: Option<String>,
     pub verified: Option<bool>,
+    pub stats: Option<bool>,
 }
 
 #[derive(Serialize)]
 pub struct CommitTeaser {
     pub header: Header,
     pub context: CommitContext,
+    /// Change stats against the first parent, only computed on request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<radicle_source::commit::Stats>,
 }
 
 #[derive(Serialize)]
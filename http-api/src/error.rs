@@ -1,11 +1,14 @@
 #![allow(clippy::large_enum_variant)]
-use axum::http::StatusCode;
+use axum::http::{header, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde_json::json;
 
 use radicle_source::surf;
 
+/// Seconds after which clients should retry a request that failed because of load.
+pub const RETRY_AFTER: &str = "1";
+
 /// Errors that may occur when interacting with [`librad::net::peer::Peer`].
 #[derive(Debug, thiserror::Error)]
 pub enum Error {
@@ -65,6 +68,14 @@ pub enum Error {
     #[error("{0}")]
     Pool(String),
 
+    /// No storage object became available in time, because of too many concurrent requests.
+    #[error("storage pool exhausted")]
+    PoolExhausted,
+
+    /// Browsing the project source took longer than allowed.
+    #[error("timed out browsing project source")]
+    Timeout,
+
     /// An error occurred with a project.
     #[error(transparent)]
     Project(#[from] radicle_common::project::Error),
@@ -114,7 +125,18 @@ impl IntoResponse for Error {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Some(e.message().to_owned()),
             ),
+            Error::PoolExhausted => {
+                tracing::warn!("Error: {}", &self);
+
+                (StatusCode::SERVICE_UNAVAILABLE, None)
+            }
+            Error::Timeout => {
+                tracing::warn!("Error: {}", &self);
+
+                (StatusCode::GATEWAY_TIMEOUT, None)
+            }
             _ => {
+                // Logged within the request span, which carries the request id.
                 tracing::error!("Error: {:?}", &self);
 
                 (StatusCode::INTERNAL_SERVER_ERROR, None)
@@ -126,6 +148,10 @@ impl IntoResponse for Error {
             "code": status.as_u16()
         }));
 
+        if status == StatusCode::SERVICE_UNAVAILABLE {
+            // This is transient, so let the client know it can back off and retry.
+            return (status, [(header::RETRY_AFTER, RETRY_AFTER)], body).into_response();
+        }
         (status, body).into_response()
     }
 }
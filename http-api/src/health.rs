@@ -0,0 +1,87 @@
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde_json::json;
+
+use crate::Context;
+
+/// How long to wait for a storage object before reporting the service as unhealthy.
+const HEALTH_CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+
+pub fn router(ctx: Context) -> Router {
+    Router::new()
+        .route("/healthz", get(health_handler))
+        .route("/readyz", get(ready_handler))
+        .layer(Extension(ctx))
+}
+
+/// Check that a storage object can be acquired from the pool.
+/// `GET /healthz`
+async fn health_handler(Extension(ctx): Extension<Context>) -> impl IntoResponse {
+    status(storage_available(&ctx).await)
+}
+
+/// Check that the service can serve requests: storage is available and the git directory is
+/// accessible.
+/// `GET /readyz`
+async fn ready_handler(Extension(ctx): Extension<Context>) -> impl IntoResponse {
+    let ready = storage_available(&ctx).await && ctx.paths.git_dir().is_dir();
+
+    status(ready)
+}
+
+async fn storage_available(ctx: &Context) -> bool {
+    matches!(
+        tokio::time::timeout(HEALTH_CHECK_TIMEOUT, ctx.storage()).await,
+        Ok(Ok(_))
+    )
+}
+
+fn status(ok: bool) -> impl IntoResponse {
+    if ok {
+        (StatusCode::OK, Json(json!({ "status": "ok" })))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "status": "unavailable" })),
+        )
+    }
+}
+
+#[cfg(test)]
+mod routes {
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_extra::setup;
+
+    const THEME: &str = "base16-ocean.dark";
+
+    #[tokio::test]
+    async fn test_health_routes() {
+        let (profile, signer, _, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+
+        for uri in ["/healthz", "/readyz"] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+
+            assert_eq!(body["status"], "ok");
+        }
+    }
+}
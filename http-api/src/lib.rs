@@ -1,35 +1,45 @@
 #![allow(clippy::if_same_then_else)]
 mod auth;
 mod axum_extra;
+mod cache;
 mod commit;
 mod error;
+mod health;
 mod project;
 mod v1;
+mod well_known;
 
 #[cfg(test)]
 mod test_extra;
 
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto as _};
 use std::path::PathBuf;
-use std::sync::Arc;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
 use std::time::{self, Duration};
 use std::{env, net};
 
 use axum::body::BoxBody;
-use axum::http::header::{AUTHORIZATION, CONTENT_TYPE};
-use axum::http::Method;
+use axum::headers::ETag;
+use axum::http::header::{ACCEPT_RANGES, AUTHORIZATION, CONTENT_RANGE, CONTENT_TYPE};
+use axum::http::{Extensions, HeaderMap, HeaderValue, Method, StatusCode, Version};
 use axum::response::{IntoResponse, Json};
 use axum::routing::get;
 use axum::{Extension, Router};
 use axum_server::tls_rustls::RustlsConfig;
 use chrono::Utc;
+use hyper::http::uri::Authority;
 use hyper::http::{Request, Response};
 use hyper::Body;
 use serde_json::json;
+use tokio::signal::unix::{signal, SignalKind};
 use tokio::sync::RwLock;
+use tower_http::compression::predicate::{DefaultPredicate, Predicate};
+use tower_http::compression::CompressionLayer;
 use tower_http::cors::{self, CorsLayer};
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
 use tracing::Span;
 
@@ -46,14 +56,48 @@ use radicle_common::{cobs, keys, person};
 use radicle_source::surf::vcs::git;
 
 use crate::auth::AuthState;
+use crate::cache::Cache;
 use crate::project::{Info, PeerInfo};
 
 use error::Error;
 
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
-pub const POPULATE_FINGERPRINTS_INTERVAL: time::Duration = time::Duration::from_secs(180);
+/// Interval at which a single project's fingerprints are refreshed, in round-robin.
+pub const POPULATE_FINGERPRINTS_INTERVAL: time::Duration = time::Duration::from_secs(20);
+/// Interval at which the fingerprints of all projects are rebuilt.
+pub const REBUILD_FINGERPRINTS_INTERVAL: time::Duration = time::Duration::from_secs(60 * 30);
+/// Time for which the issue and patch counts of a project are cached.
+pub const COUNTS_TTL: time::Duration = time::Duration::from_secs(60);
+/// Time for which a commit signer that isn't a tracked peer of a project is remembered as such,
+/// before the project's fingerprints are refreshed again to look for it.
+pub const SIGNER_MISS_TTL: time::Duration = time::Duration::from_secs(60);
+/// Time for which seed stats are cached, since computing them scans all projects.
+pub const STATS_TTL: time::Duration = time::Duration::from_secs(60);
 pub const CLEANUP_SESSIONS_INTERVAL: time::Duration = time::Duration::from_secs(60);
+/// Default time by which an authorized session is extended when it is refreshed.
+pub const AUTHORIZED_SESSIONS_TTL: time::Duration = time::Duration::from_secs(60 * 60 * 24);
+/// Default maximum size in bytes of a session request body, eg. a signed SIWE message.
+pub const SESSION_BODY_LIMIT: usize = 8 * 1024;
+/// Default number of storage objects kept in the pool, ie. the number of concurrent storage
+/// accesses. Each object holds open file descriptors to the monorepo.
 pub const STORAGE_POOL_SIZE: usize = 10;
+/// Time to wait for a storage object to become available, before rejecting the request.
+pub const STORAGE_POOL_TIMEOUT: time::Duration = time::Duration::from_secs(10);
+/// Default time to wait for source browsing, eg. walking the history of a project, before
+/// responding with a timeout.
+pub const BROWSE_TIMEOUT: time::Duration = time::Duration::from_secs(30);
+/// File names tried, in order, when looking for a project README.
+pub const README_FILES: &[&str] = &[
+    "README",
+    "README.md",
+    "README.markdown",
+    "README.txt",
+    "README.rst",
+    "Readme.md",
+];
+/// Header used to correlate a request with its logs. Taken from the request if present,
+/// otherwise generated, and echoed back in the response.
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
 
 #[derive(Debug, Clone)]
 pub struct Options {
@@ -63,6 +107,27 @@ pub struct Options {
     pub tls_cert: Option<PathBuf>,
     pub tls_key: Option<PathBuf>,
     pub theme: String,
+    pub session_ttl: Duration,
+    pub session_body_limit: usize,
+    pub siwe_domains: Vec<String>,
+    pub disable_siwe: bool,
+    pub cors_origins: Vec<String>,
+    pub readmes: Vec<String>,
+    pub storage_pool_size: usize,
+    pub cache_size: usize,
+    pub browse_timeout: Duration,
+}
+
+/// Issue and patch counts of a project.
+///
+/// These are recomputed when the project head changes, which is usually the case after a push,
+/// or after [`COUNTS_TTL`], since collaborative objects may be updated on their own.
+#[derive(Debug, Clone, Copy)]
+struct Counts {
+    head: Option<git2::Oid>,
+    issues: usize,
+    patches: usize,
+    computed: time::Instant,
 }
 
 /// SSH Key fingerprint.
@@ -78,17 +143,46 @@ pub struct Context {
     theme: String,
     pool: Pool<Storage>,
     peer_id: PeerId,
+    session_ttl: Duration,
+    /// Maximum size in bytes of a session request body.
+    session_body_limit: usize,
+    /// Domains accepted in Sign-In With Ethereum messages.
+    siwe_domains: Vec<Authority>,
+    /// File names tried when looking for a project README.
+    readmes: Vec<String>,
+    /// Cache of commits, keyed on commit hash.
+    commit_cache: Cache<radicle_source::Commit>,
+    /// Cache of commit histories, keyed on head commit hash.
+    history_cache: Cache<radicle_source::commit::Commits>,
+    /// Time to wait for source browsing, which runs on the blocking thread pool.
+    browse_timeout: Duration,
     aliases: Arc<RwLock<HashMap<String, Urn>>>,
     projects: Arc<RwLock<HashMap<Urn, Fingerprints>>>,
+    /// Issue and patch counts of projects, since counting collaborative objects is expensive.
+    counts: Arc<Mutex<HashMap<Urn, Counts>>>,
+    /// Commit signer fingerprints not found among a project's tracked peers, along with
+    /// when they were looked up.
+    signer_misses: Arc<Mutex<HashMap<(Urn, Fingerprint), time::Instant>>>,
+    /// Seed stats, along with the time they were computed.
+    stats: Arc<RwLock<Option<(time::Instant, serde_json::Value)>>>,
     sessions: Arc<RwLock<HashMap<SessionId, AuthState>>>,
 }
 
 impl Context {
     fn new(paths: Paths, signer: BoxedSigner, theme: String) -> Self {
+        Self::with_pool_size(paths, signer, theme, STORAGE_POOL_SIZE)
+    }
+
+    fn with_pool_size(
+        paths: Paths,
+        signer: BoxedSigner,
+        theme: String,
+        storage_pool_size: usize,
+    ) -> Self {
         let peer_id = signer.peer_id();
         let pool = storage::Pool::new(
             storage::pool::ReadWriteConfig::new(paths.clone(), signer, Initialised::no()),
-            STORAGE_POOL_SIZE,
+            storage_pool_size,
         );
 
         Self {
@@ -96,16 +190,26 @@ impl Context {
             pool,
             theme,
             peer_id,
+            session_ttl: AUTHORIZED_SESSIONS_TTL,
+            session_body_limit: SESSION_BODY_LIMIT,
+            siwe_domains: Vec::new(),
+            readmes: README_FILES.iter().map(|f| f.to_string()).collect(),
+            commit_cache: Cache::new(0),
+            history_cache: Cache::new(0),
+            browse_timeout: BROWSE_TIMEOUT,
             aliases: Default::default(),
             projects: Default::default(),
+            counts: Default::default(),
+            signer_misses: Default::default(),
+            stats: Default::default(),
             sessions: Default::default(),
         }
     }
 
     async fn storage(&self) -> Result<deadpool::managed::Object<Storage, InitError>, Error> {
-        self.pool
-            .get()
+        tokio::time::timeout(STORAGE_POOL_TIMEOUT, self.pool.get())
             .await
+            .map_err(|_| Error::PoolExhausted)?
             .map_err(|e| Error::Pool(e.to_string()))
     }
 
@@ -130,6 +234,33 @@ impl Context {
         Ok(())
     }
 
+    /// Resolve a project alias to its URN, rebuilding the alias cache if it isn't found.
+    async fn resolve_alias(&self, alias: &str) -> Result<Urn, Error> {
+        let mut aliases = self.aliases.write().await;
+        if !aliases.contains_key(alias) {
+            // If the alias does not exist, rebuild the cache.
+            self.populate_aliases(&mut aliases).await?;
+        }
+
+        aliases.get(alias).cloned().ok_or(Error::NotFound)
+    }
+
+    /// Check that the given session exists, is signed in and hasn't expired.
+    async fn authorize(&self, id: &str) -> Result<(), Error> {
+        let sessions = self.sessions.read().await;
+
+        match sessions.get(id) {
+            Some(AuthState::Authorized(session)) => match session.expiration_time {
+                Some(expiration_time) if Utc::now() >= expiration_time => {
+                    Err(Error::Auth("Session expired"))
+                }
+                _ => Ok(()),
+            },
+            Some(AuthState::Unauthorized { .. }) => Err(Error::Auth("Session not authorized")),
+            None => Err(Error::Auth("Session not found")),
+        }
+    }
+
     fn cleanup_sessions(&self, map: &mut HashMap<SessionId, AuthState>) -> Result<(), Error> {
         let mut to_remove: Vec<SessionId> = Vec::new();
 
@@ -160,55 +291,212 @@ impl Context {
         Ok(())
     }
 
-    /// Populate a map between SSH fingerprints and their peer identities
-    fn populate_fingerprints(
-        map: &mut tokio::sync::RwLockWriteGuard<HashMap<Urn, Fingerprints>>,
+    /// Compute the map between SSH fingerprints and their peer identities, for all projects.
+    fn all_fingerprints(
         storage: deadpool::managed::Object<Storage, InitError>,
-    ) -> Result<(), Error> {
+    ) -> Result<HashMap<Urn, Fingerprints>, Error> {
         let identities = identities::any::list(storage.read_only())?;
+        let mut map = HashMap::new();
 
         for identity in identities.flatten() {
             if let SomeIdentity::Project(project) = identity {
                 let meta = project::Metadata::try_from(project)?;
-                let fingerprints = map.entry(meta.urn.clone()).or_default();
-                let tracked = project::tracked(&meta, storage.read_only())?;
+                let fingerprints = Self::project_fingerprints(&meta, &storage)?;
 
-                for peer in tracked {
-                    let fp = keys::to_ssh_fingerprint(&peer.id).expect("Conversion cannot fail");
-                    fingerprints.insert(fp, peer);
-                }
+                map.insert(meta.urn, fingerprints);
             }
         }
 
+        Ok(map)
+    }
+
+    /// Get the SSH fingerprints of a project's tracked peers.
+    fn project_fingerprints(
+        meta: &project::Metadata,
+        storage: &Storage,
+    ) -> Result<Fingerprints, Error> {
+        let tracked = project::tracked(meta, storage.read_only())?;
+
+        Ok(tracked
+            .into_iter()
+            .map(|peer| {
+                let fp = keys::to_ssh_fingerprint(&peer.id).expect("Conversion cannot fail");
+                (fp, peer)
+            })
+            .collect())
+    }
+
+    /// Populate the fingerprints of a single project.
+    async fn populate_fingerprints_for(&self, urn: &Urn) -> Result<(), Error> {
+        let storage = self.storage().await?;
+        let project = identities::project::get(&*storage, urn)?.ok_or(Error::NotFound)?;
+        let meta: project::Metadata = project.try_into()?;
+        let fingerprints = Self::project_fingerprints(&meta, &storage)?;
+
+        self.projects
+            .write()
+            .await
+            .entry(urn.clone())
+            .or_default()
+            .extend(fingerprints);
+
         Ok(())
     }
 
+    /// Purge the cached responses of a project, eg. after a force-push.
+    /// Returns the number of commit and history responses purged.
+    fn purge_cache(&self, urn: &Urn) -> usize {
+        self.counts
+            .lock()
+            .expect("Context::purge_cache: lock is not poisoned")
+            .remove(urn);
+
+        self.commit_cache.purge(urn) + self.history_cache.purge(urn)
+    }
+
     /// From a commit hash, return the signer's fingerprint, if any.
     fn commit_ssh_fingerprint(&self, sha1: &str) -> Result<Option<String>, Error> {
         radicle_common::git::commit_ssh_fingerprint(self.paths.git_dir(), sha1)
             .map_err(|e| Error::Io("failed to get commit's ssh fingerprint", e))
     }
 
-    async fn project_info(&self, urn: Urn) -> Result<Info, Error> {
+    /// Get the tracked peers who signed the given commits, if any.
+    ///
+    /// When a signature isn't found in the cache, the project's fingerprints are refreshed once,
+    /// so that commits pushed since the last periodic refresh are resolved. Signers that still
+    /// aren't found don't cause another refresh for [`SIGNER_MISS_TTL`].
+    async fn commit_signers(
+        &self,
+        urn: &Urn,
+        shas: &[String],
+    ) -> Result<Vec<Option<PeerInfo>>, Error> {
+        let fps = shas
+            .iter()
+            .map(|sha| self.commit_ssh_fingerprint(sha))
+            .collect::<Result<Vec<_>, _>>()?;
+        let missing = {
+            let projects = self.projects.read().await;
+            let known = projects.get(urn);
+
+            fps.iter()
+                .flatten()
+                .filter(|fp| !known.map_or(false, |fps| fps.contains_key(*fp)))
+                .cloned()
+                .collect::<Vec<_>>()
+        };
+        if self.record_signer_misses(urn, missing) {
+            self.populate_fingerprints_for(urn).await?;
+        }
+
+        let projects = self.projects.read().await;
+        let known = projects.get(urn);
+
+        Ok(fps
+            .into_iter()
+            .map(|fp| known?.get(&fp?).cloned())
+            .collect())
+    }
+
+    /// Remember the given signers as missing from a project's tracked peers.
+    /// Returns whether any of them wasn't already known to be missing.
+    fn record_signer_misses(&self, urn: &Urn, fingerprints: Vec<Fingerprint>) -> bool {
+        let mut misses = self
+            .signer_misses
+            .lock()
+            .expect("Context::record_signer_misses: lock is not poisoned");
+        let now = time::Instant::now();
+        let mut new = false;
+
+        misses.retain(|_, looked_up| now.duration_since(*looked_up) < SIGNER_MISS_TTL);
+
+        for fp in fingerprints {
+            if let Entry::Vacant(e) = misses.entry((urn.clone(), fp)) {
+                e.insert(now);
+                new = true;
+            }
+        }
+        new
+    }
+
+    /// Compute the entity tag of a project's info from its current head and cached issue
+    /// and patch counts, without scanning its collaborative objects.
+    /// Returns `None` if the counts aren't cached.
+    async fn project_etag(&self, urn: &Urn) -> Result<Option<ETag>, Error> {
         let storage = self.storage().await?;
-        let project = identities::project::get(&*storage, &urn)?.ok_or(Error::NotFound)?;
+        let project = identities::project::get(&*storage, urn)?.ok_or(Error::NotFound)?;
         let meta: project::Metadata = project.try_into()?;
 
         let repo = git2::Repository::open_bare(self.paths.git_dir())?;
-        let head = get_head_commit(&repo, &urn, &meta.default_branch, &meta.delegates)
+        let head = get_head_commit(&repo, urn, &meta.default_branch, &meta.delegates)
             .map(|h| h.id)
             .ok();
 
-        let whoami = person::local(&*storage).map_err(Error::LocalIdentity)?;
-        let cobs = cobs::Store::new(whoami, &self.paths, &storage);
-        let issues = cobs.issues();
-        let issues = issues.count(&urn).map_err(Error::Cobs)?;
+        Ok(self
+            .cached_counts(urn, head)
+            .map(|(issues, patches)| project::etag(head, issues, patches)))
+    }
+
+    /// Get the cached issue and patch counts of a project, if they are still valid for the
+    /// given head.
+    fn cached_counts(&self, urn: &Urn, head: Option<git2::Oid>) -> Option<(usize, usize)> {
+        let counts = self
+            .counts
+            .lock()
+            .expect("Context::cached_counts: lock is not poisoned");
+
+        counts
+            .get(urn)
+            .filter(|c| c.head == head && c.computed.elapsed() < COUNTS_TTL)
+            .map(|c| (c.issues, c.patches))
+    }
 
-        let patches = cobs.patches();
-        let patches = patches.count(&urn).map_err(Error::Cobs)?;
+    /// Get the issue and patch counts of a project at the given head, counting its
+    /// collaborative objects if they aren't cached.
+    fn project_counts(
+        &self,
+        urn: &Urn,
+        head: Option<git2::Oid>,
+        storage: &Storage,
+    ) -> Result<(usize, usize), Error> {
+        if let Some(counts) = self.cached_counts(urn, head) {
+            return Ok(counts);
+        }
+        let whoami = person::local(storage).map_err(Error::LocalIdentity)?;
+        let cobs = cobs::Store::new(whoami, &self.paths, storage);
+        let issues = cobs.issues().count(urn).map_err(Error::Cobs)?;
+        let patches = cobs.patches().count(urn).map_err(Error::Cobs)?;
+
+        self.counts
+            .lock()
+            .expect("Context::project_counts: lock is not poisoned")
+            .insert(
+                urn.clone(),
+                Counts {
+                    head,
+                    issues,
+                    patches,
+                    computed: time::Instant::now(),
+                },
+            );
+
+        Ok((issues, patches))
+    }
+
+    async fn project_info(&self, urn: Urn) -> Result<Info, Error> {
+        let storage = self.storage().await?;
+        let project = identities::project::get(&*storage, &urn)?.ok_or(Error::NotFound)?;
+        let meta: project::Metadata = project.try_into()?;
+
+        let repo = git2::Repository::open_bare(self.paths.git_dir())?;
+        let (head, head_peer) = get_head(&repo, &urn, &meta.default_branch, &meta.delegates)
+            .map(|(h, peer)| (Some(h.id), peer))
+            .unwrap_or_default();
+
+        let (issues, patches) = self.project_counts(&urn, head, &storage)?;
 
         Ok(Info {
             head,
+            head_peer,
             meta,
             issues,
             patches,
@@ -220,13 +508,29 @@ impl Context {
 pub async fn run(options: Options) -> anyhow::Result<()> {
     let (_, profile, signer) = shared::profile(options.root, options.passphrase)?;
     let paths = profile.paths();
-    let ctx = Context::new(paths.clone(), signer, options.theme);
+    let mut ctx = Context::with_pool_size(
+        paths.clone(),
+        signer,
+        options.theme,
+        options.storage_pool_size,
+    );
+    ctx.session_ttl = options.session_ttl;
+    ctx.session_body_limit = options.session_body_limit;
+    ctx.readmes.extend(options.readmes);
+    ctx.commit_cache = Cache::new(options.cache_size);
+    ctx.history_cache = Cache::new(options.cache_size);
+    ctx.browse_timeout = options.browse_timeout;
+
+    if !options.disable_siwe {
+        ctx.siwe_domains = siwe_domains(options.siwe_domains)?;
+    }
     let peer_id = ctx.peer_id;
 
     // Populate fingerprints
     tokio::spawn(populate_fingerprints_job(
         ctx.clone(),
         POPULATE_FINGERPRINTS_INTERVAL,
+        REBUILD_FINGERPRINTS_INTERVAL,
     ));
     // Cleanup sessions
     tokio::spawn(cleanup_sessions_job(ctx.clone(), CLEANUP_SESSIONS_INTERVAL));
@@ -235,21 +539,44 @@ pub async fn run(options: Options) -> anyhow::Result<()> {
         .route("/", get(root_handler))
         .layer(Extension(peer_id));
 
+    let cors_layer = CorsLayer::new()
+        .max_age(Duration::from_secs(86400))
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+        ])
+        .allow_headers([CONTENT_TYPE, AUTHORIZATION]);
+    let cors_layer = if options.cors_origins.is_empty() {
+        cors_layer.allow_origin(cors::Any)
+    } else {
+        // Credentials may only be allowed with an explicit list of origins.
+        cors_layer
+            .allow_origin(cors_origins(&options.cors_origins)?)
+            .allow_credentials(true)
+    };
+
     let app = Router::new()
         .merge(root_router)
+        .merge(health::router(ctx.clone()))
+        .merge(well_known::router(ctx.clone()))
         .merge(v1::router(ctx.clone()))
-        .layer(
-            CorsLayer::new()
-                .max_age(Duration::from_secs(86400))
-                .allow_origin(cors::Any)
-                .allow_methods([Method::GET, Method::POST, Method::PUT])
-                .allow_headers([CONTENT_TYPE, AUTHORIZATION]),
-        )
+        .layer(cors_layer)
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(|request: &Request<Body>| {
+                    // Set by `SetRequestIdLayer`, either from the client or generated.
+                    let request_id = request
+                        .headers()
+                        .get(REQUEST_ID_HEADER)
+                        .and_then(|id| id.to_str().ok())
+                        .unwrap_or_default();
+
                     tracing::info_span!(
                         "request",
+                        id = %request_id,
                         method = %request.method(),
                         uri = %request.uri(),
                         status = tracing::field::Empty,
@@ -264,25 +591,112 @@ pub async fn run(options: Options) -> anyhow::Result<()> {
                         tracing::info!("Processed");
                     },
                 ),
-        );
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
+        .layer(CompressionLayer::new().compress_when(DefaultPredicate::new().and(not_ranged)));
 
     if let (Some(cert), Some(key)) = (options.tls_cert, options.tls_key) {
-        let config = RustlsConfig::from_pem_file(cert, key).await.unwrap();
+        let config = RustlsConfig::from_pem_file(&cert, &key).await.unwrap();
+
+        tokio::spawn(reload_tls_on_hangup(config.clone(), cert, key));
+
+        let handle = axum_server::Handle::new();
+
+        tokio::spawn({
+            let handle = handle.clone();
+            async move {
+                shared::shutdown_signal().await;
+                handle.graceful_shutdown(None);
+            }
+        });
 
         tracing::info!("listening on https://{}", options.listen);
         axum_server::bind_rustls(options.listen, config)
+            .handle(handle)
             .serve(app.into_make_service())
             .await?;
     } else {
         tracing::info!("listening on http://{}", options.listen);
         axum::Server::bind(&options.listen)
             .serve(app.into_make_service())
+            .with_graceful_shutdown(shared::shutdown_signal())
             .await?;
     }
 
     Ok(())
 }
 
+/// Reload the TLS certificate and key from disk whenever the process receives `SIGHUP`, eg.
+/// after a certificate renewal. Established connections are unaffected. If the new files
+/// can't be loaded, the current certificate is kept.
+async fn reload_tls_on_hangup(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    let mut hangup = match signal(SignalKind::hangup()) {
+        Ok(hangup) => hangup,
+        Err(err) => {
+            tracing::error!("Failed to listen for SIGHUP, TLS reload disabled: {}", err);
+            return;
+        }
+    };
+
+    while hangup.recv().await.is_some() {
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => tracing::info!("TLS certificate reloaded from {:?}", cert),
+            Err(err) => tracing::error!(
+                "Failed to reload TLS certificate, keeping the current one: {}",
+                err
+            ),
+        }
+    }
+}
+
+/// Whether a response may be compressed.
+///
+/// Responses supporting byte ranges are left as-is, since ranges refer to the uncompressed body.
+fn not_ranged(_: StatusCode, _: Version, headers: &HeaderMap, _: &Extensions) -> bool {
+    !headers.contains_key(CONTENT_RANGE) && !headers.contains_key(ACCEPT_RANGES)
+}
+
+/// Parse the origins allowed to make cross-origin requests, eg. `https://app.radicle.network`.
+fn cors_origins(origins: &[String]) -> anyhow::Result<Vec<HeaderValue>> {
+    origins
+        .iter()
+        .map(|o| {
+            HeaderValue::from_str(o).map_err(|e| anyhow::anyhow!("Invalid origin {:?}: {}", o, e))
+        })
+        .collect()
+}
+
+/// Get the list of domains allowed for Sign-In With Ethereum.
+///
+/// If no domain was configured, falls back to the `RADICLE_DOMAIN` environment variable.
+fn siwe_domains(domains: Vec<String>) -> anyhow::Result<Vec<Authority>> {
+    let domains = if domains.is_empty() {
+        env::var("RADICLE_DOMAIN")
+            .unwrap_or_default()
+            .split(',')
+            .map(|d| d.trim().to_owned())
+            .filter(|d| !d.is_empty())
+            .collect()
+    } else {
+        domains
+    };
+
+    if domains.is_empty() {
+        anyhow::bail!(
+            "No Sign-In With Ethereum domain configured: use `--siwe-domain` or set `RADICLE_DOMAIN`, \
+             or disable it with `--disable-siwe`"
+        );
+    }
+
+    domains
+        .iter()
+        .map(|d| {
+            Authority::from_str(d).map_err(|e| anyhow::anyhow!("Invalid domain {:?}: {}", d, e))
+        })
+        .collect()
+}
+
 async fn cleanup_sessions_job(ctx: Context, interval: time::Duration) {
     let mut timer = tokio::time::interval(interval);
 
@@ -296,18 +710,58 @@ async fn cleanup_sessions_job(ctx: Context, interval: time::Duration) {
     }
 }
 
-async fn populate_fingerprints_job(ctx: Context, interval: time::Duration) -> Result<(), Error> {
+/// Keep the fingerprints of projects up to date.
+///
+/// On every tick, a single project is refreshed, cycling through the projects found by the
+/// last full rebuild, which runs every `rebuild_interval`. Projects created in between are
+/// populated on demand, the first time one of their signers isn't known.
+async fn populate_fingerprints_job(
+    ctx: Context,
+    interval: time::Duration,
+    rebuild_interval: time::Duration,
+) -> Result<(), Error> {
     let mut timer = tokio::time::interval(interval);
+    let mut last_rebuild: Option<time::Instant> = None;
+    let mut queue: VecDeque<Urn> = VecDeque::new();
 
     loop {
         timer.tick().await; // Returns immediately the first time.
 
-        let storage = ctx.storage().await?;
-        let mut projects_guard: tokio::sync::RwLockWriteGuard<_> = ctx.projects.write().await;
-        if let Err(err) = tokio::task::block_in_place(|| {
-            Context::populate_fingerprints(&mut projects_guard, storage)
-        }) {
-            tracing::error!("Failed to populate project fingerprints: {}", err);
+        if last_rebuild.map_or(true, |t| t.elapsed() >= rebuild_interval) {
+            let storage = match ctx.storage().await {
+                Ok(storage) => storage,
+                Err(err) => {
+                    tracing::error!("Failed to populate project fingerprints: {}", err);
+                    continue;
+                }
+            };
+            // Computed without holding the lock, so that requests aren't held up meanwhile.
+            match tokio::task::block_in_place(|| Context::all_fingerprints(storage)) {
+                Ok(fingerprints) => {
+                    queue = fingerprints.keys().cloned().collect();
+                    *ctx.projects.write().await = fingerprints;
+                }
+                Err(err) => {
+                    tracing::error!("Failed to populate project fingerprints: {}", err);
+                }
+            }
+            last_rebuild = Some(time::Instant::now());
+
+            continue;
+        }
+
+        if let Some(urn) = queue.pop_front() {
+            match ctx.populate_fingerprints_for(&urn).await {
+                Ok(()) => queue.push_back(urn),
+                // The project was removed since the last rebuild.
+                Err(Error::NotFound) => {
+                    ctx.projects.write().await.remove(&urn);
+                }
+                Err(err) => {
+                    tracing::error!("Failed to populate fingerprints for {}: {}", urn, err);
+                    queue.push_back(urn);
+                }
+            }
         }
     }
 }
@@ -334,6 +788,11 @@ async fn root_handler(Extension(peer_id): Extension<PeerId>) -> impl IntoRespons
                 "href": "/v1/delegates/:urn/projects",
                 "rel": "projects",
                 "type": "GET"
+            },
+            {
+                "href": "/.well-known/radicle/:alias",
+                "rel": "project",
+                "type": "GET"
             }
         ]
     });
@@ -347,13 +806,24 @@ fn get_head_commit(
     default_branch: &str,
     delegates: &[project::Delegate],
 ) -> Result<git::Commit, Error> {
+    get_head(repo, urn, default_branch, delegates).map(|(commit, _)| commit)
+}
+
+/// Get the head commit of a project's default branch, along with the delegate it was
+/// resolved from, when there is no local branch to resolve it from.
+fn get_head(
+    repo: &git2::Repository,
+    urn: &Urn,
+    default_branch: &str,
+    delegates: &[project::Delegate],
+) -> Result<(git::Commit, Option<PeerId>), Error> {
     let namespace = Namespace::try_from(urn).map_err(|_| Error::MissingNamespace)?;
     let branch = One::try_from(default_branch).map_err(|_| Error::MissingDefaultBranch)?;
     let local = Reference::head(namespace.clone(), None, branch.clone()).to_string();
     let result = repo.find_reference(&local);
 
-    let head = match result {
-        Ok(b) => b,
+    let (head, peer) = match result {
+        Ok(b) => (b, None),
         Err(_) => {
             tracing::debug!("No local head, falling back to project delegates");
             let resolved_default_delegate = match delegates {
@@ -375,9 +845,11 @@ fn get_head_commit(
                 }
             }?;
             let remote = Reference::head(namespace, *resolved_default_delegate, branch).to_string();
+            let head = repo
+                .find_reference(&remote)
+                .map_err(|_| Error::NoHead("history lookup failed"))?;
 
-            repo.find_reference(&remote)
-                .map_err(|_| Error::NoHead("history lookup failed"))?
+            (head, Some(*resolved_default_delegate))
         }
     };
     let oid = head
@@ -385,7 +857,7 @@ fn get_head_commit(
         .ok_or(Error::NoHead("head target not found"))?;
     let commit = repo.find_commit(oid)?.try_into()?;
 
-    Ok(commit)
+    Ok((commit, peer))
 }
 
 #[cfg(test)]
@@ -1,6 +1,7 @@
 use std::net;
 use std::path::PathBuf;
 use std::process;
+use std::time;
 
 use radicle_http_api as api;
 
@@ -32,6 +33,50 @@ pub struct Options {
     /// syntax highlight theme
     #[argh(option, default = r#"String::from("base16-ocean.dark")"#)]
     pub theme: String,
+
+    /// number of seconds an authorized session is extended by when refreshed (default: 86400)
+    #[argh(option, default = "api::AUTHORIZED_SESSIONS_TTL.as_secs()")]
+    pub session_ttl: u64,
+
+    /// maximum size in bytes of a session request body (default: 8192)
+    #[argh(option, default = "api::SESSION_BODY_LIMIT")]
+    pub session_body_limit: usize,
+
+    /// domain accepted for Sign-In With Ethereum; may be specified multiple times
+    /// (default: `RADICLE_DOMAIN` environment variable)
+    #[argh(option)]
+    pub siwe_domain: Vec<String>,
+
+    /// disable Sign-In With Ethereum sessions
+    #[argh(switch)]
+    pub disable_siwe: bool,
+
+    /// origin allowed to make cross-origin requests, eg. `https://app.radicle.network`; may be
+    /// specified multiple times (default: any origin)
+    #[argh(option)]
+    pub cors_origin: Vec<String>,
+
+    /// additional file name to look for when serving a project README; may be specified
+    /// multiple times
+    #[argh(option)]
+    pub readme: Vec<String>,
+
+    /// number of concurrent storage accesses; larger pools serve more requests in parallel
+    /// at the cost of more open file descriptors (default: 10)
+    #[argh(option, default = "api::STORAGE_POOL_SIZE")]
+    pub storage_pool_size: usize,
+
+    /// number of commit and history responses kept in memory, per kind (default: 0, disabled)
+    #[argh(option, default = "0")]
+    pub cache_size: usize,
+
+    /// number of seconds to wait for source browsing before responding with a timeout (default: 30)
+    #[argh(option, default = "api::BROWSE_TIMEOUT.as_secs()")]
+    pub browse_timeout: u64,
+
+    /// log output format, either `plain` or `gcp` (default: plain)
+    #[argh(option, default = "shared::LogFmt::default()")]
+    pub log_format: shared::LogFmt,
 }
 
 impl Options {
@@ -49,6 +94,15 @@ impl From<Options> for api::Options {
             tls_key: other.tls_key,
             listen: other.listen,
             theme: other.theme,
+            session_ttl: time::Duration::from_secs(other.session_ttl),
+            session_body_limit: other.session_body_limit,
+            siwe_domains: other.siwe_domain,
+            disable_siwe: other.disable_siwe,
+            cors_origins: other.cors_origin,
+            readmes: other.readme,
+            storage_pool_size: other.storage_pool_size,
+            cache_size: other.cache_size,
+            browse_timeout: time::Duration::from_secs(other.browse_timeout),
         }
     }
 }
@@ -57,7 +111,7 @@ impl From<Options> for api::Options {
 async fn main() {
     let options = Options::from_env();
 
-    shared::init_logger();
+    shared::init_logger(options.log_format);
     tracing::info!("version {}-{}", env!("CARGO_PKG_VERSION"), env!("GIT_HEAD"));
 
     match api::run(options.into()).await {
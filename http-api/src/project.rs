@@ -1,8 +1,14 @@
+use std::str::FromStr;
+
+use axum::headers::ETag;
+use chrono::{Datelike as _, SecondsFormat, TimeZone as _, Utc};
 use serde::{Deserialize, Serialize};
 
 use librad::git::storage::ReadOnly;
 use librad::git::tracking;
+use librad::PeerId;
 
+use radicle_common::cobs::{issue, patch};
 pub use radicle_common::project::{Delegate, Metadata, PeerInfo};
 
 use crate::Error;
@@ -14,6 +20,154 @@ pub struct ProjectsQueryString {
     pub per_page: Option<usize>,
 }
 
+/// State of the issues to list.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum IssueState {
+    Open,
+    Closed,
+}
+
+impl IssueState {
+    /// Check whether an issue is in this state.
+    pub fn matches(&self, issue: &issue::Issue) -> bool {
+        match self {
+            Self::Open => matches!(issue.state, issue::State::Open),
+            Self::Closed => matches!(issue.state, issue::State::Closed { .. }),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct IssuesQueryString {
+    pub state: Option<IssueState>,
+    pub sort: Option<Sort>,
+    pub order: Option<Order>,
+}
+
+/// State of the patches to list.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum PatchState {
+    /// Neither merged nor archived.
+    Open,
+    /// At least one revision was merged.
+    Merged,
+    Archived,
+}
+
+impl PatchState {
+    /// Check whether a patch is in this state.
+    pub fn matches(&self, patch: &patch::Patch) -> bool {
+        let archived = matches!(patch.state, patch::State::Archived);
+        let merged = patch.revisions.iter().any(|r| !r.merges.is_empty());
+
+        match self {
+            Self::Open => !archived && !merged,
+            Self::Merged => !archived && merged,
+            Self::Archived => archived,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct PatchesQueryString {
+    pub state: Option<PatchState>,
+    pub sort: Option<Sort>,
+    pub order: Option<Order>,
+}
+
+/// Field by which issues and patches are sorted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Sort {
+    /// Time of creation.
+    Created,
+    /// Time of the latest activity, eg. a comment or a new revision.
+    Updated,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Order {
+    Asc,
+    Desc,
+}
+
+impl Order {
+    /// Sort items by the given key, in this order.
+    pub fn sort<T, K: Ord>(self, items: &mut [T], key: impl FnMut(&T) -> K) {
+        items.sort_by_key(key);
+
+        if self == Self::Desc {
+            items.reverse();
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct ActivityQueryString {
+    pub format: Option<TimeFormat>,
+    pub bucket: Option<Bucket>,
+}
+
+/// Format of the timestamps returned.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TimeFormat {
+    /// Seconds since the Unix epoch.
+    Unix,
+    /// ISO-8601 date and time, in UTC, eg. `2022-08-01T00:00:00Z`.
+    Iso,
+}
+
+impl Default for TimeFormat {
+    fn default() -> Self {
+        Self::Unix
+    }
+}
+
+impl TimeFormat {
+    /// Format a Unix timestamp.
+    pub fn format(self, seconds: i64) -> serde_json::Value {
+        match self {
+            Self::Unix => seconds.into(),
+            Self::Iso => Utc
+                .timestamp(seconds, 0)
+                .to_rfc3339_opts(SecondsFormat::Secs, true)
+                .into(),
+        }
+    }
+}
+
+/// Time period by which timestamps are counted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Bucket {
+    Day,
+    /// Weeks start on Monday.
+    Week,
+}
+
+impl Bucket {
+    /// Get the start of the bucket a Unix timestamp falls in, as a Unix timestamp.
+    pub fn start(self, seconds: i64) -> i64 {
+        const DAY: i64 = 24 * 60 * 60;
+
+        let day = seconds - seconds.rem_euclid(DAY);
+        match self {
+            Self::Day => day,
+            Self::Week => {
+                let weekday = Utc.timestamp(day, 0).weekday().num_days_from_monday();
+                day - weekday as i64 * DAY
+            }
+        }
+    }
+}
+
 /// Project info.
 #[derive(Serialize)]
 #[serde(rename_all = "camelCase")]
@@ -25,10 +179,27 @@ pub struct Info {
     /// branches have been replicated on this node.
     #[serde(with = "option")]
     pub head: Option<git2::Oid>,
+    /// Delegate the head was resolved from, if the project has no local default branch.
+    pub head_peer: Option<PeerId>,
     pub patches: usize,
     pub issues: usize,
 }
 
+impl Info {
+    /// Entity tag of this project info, see [`etag`].
+    pub fn etag(&self) -> ETag {
+        etag(self.head, self.issues, self.patches)
+    }
+}
+
+/// Weak entity tag of a project's info, derived from its head and issue and patch counts.
+pub fn etag(head: Option<git2::Oid>, issues: usize, patches: usize) -> ETag {
+    let head = head.map(|h| h.to_string()).unwrap_or_default();
+
+    ETag::from_str(&format!("W/\"{}-{}-{}\"", head, issues, patches))
+        .expect("etag: tag is well-formed")
+}
+
 pub fn tracked<S: AsRef<ReadOnly>>(meta: &Metadata, storage: &S) -> Result<Vec<PeerInfo>, Error> {
     let tracked =
         tracking::tracked(storage.as_ref(), Some(&meta.urn)).map_err(|_| Error::NotFound)?;
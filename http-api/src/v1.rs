@@ -4,6 +4,8 @@ mod projects;
 mod sessions;
 mod stats;
 
+use axum::http::header::LINK;
+use axum::http::{HeaderMap, HeaderValue, Uri};
 use axum::Router;
 
 use crate::Context;
@@ -18,3 +20,44 @@ pub fn router(ctx: Context) -> Router {
 
     Router::new().nest("/v1", routes)
 }
+
+/// Build the `Link` header pointing to the previous and next pages of a paginated listing,
+/// eg. `</v1/projects?page=2>; rel="next"`.
+///
+/// There is assumed to be a next page when the current page is full.
+fn pagination_links(uri: &Uri, page: usize, per_page: usize, count: usize) -> HeaderMap {
+    let params = uri
+        .query()
+        .unwrap_or_default()
+        .split('&')
+        .filter(|p| !p.is_empty() && !p.starts_with("page="))
+        .collect::<Vec<_>>();
+    let link = |page: usize, rel: &str| {
+        let page = format!("page={}", page);
+        let query = params
+            .iter()
+            .copied()
+            .chain(std::iter::once(page.as_str()))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        format!("<{}?{}>; rel=\"{}\"", uri.path(), query, rel)
+    };
+
+    let mut links = Vec::new();
+    if page > 0 {
+        links.push(link(page - 1, "prev"));
+    }
+    if count > 0 && count == per_page {
+        links.push(link(page + 1, "next"));
+    }
+
+    let mut headers = HeaderMap::new();
+    if links.is_empty() {
+        return headers;
+    }
+    if let Ok(value) = HeaderValue::from_str(&links.join(", ")) {
+        headers.insert(LINK, value);
+    }
+    headers
+}
@@ -1,5 +1,6 @@
 use std::convert::TryInto;
 
+use axum::extract::OriginalUri;
 use axum::response::IntoResponse;
 use axum::routing::get;
 use axum::{Extension, Json, Router};
@@ -11,7 +12,9 @@ use radicle_common::{cobs, person};
 
 use crate::axum_extra::{Path, Query};
 use crate::project::{self, Info};
-use crate::{get_head_commit, Context, Error};
+use crate::{get_head, Context, Error};
+
+use super::pagination_links;
 
 pub fn router(ctx: Context) -> Router {
     Router::new()
@@ -26,6 +29,7 @@ pub fn router(ctx: Context) -> Router {
 /// `GET /delegates/:delegate/projects`
 async fn delegates_projects_handler(
     Extension(ctx): Extension<Context>,
+    OriginalUri(uri): OriginalUri,
     Path(delegate): Path<Urn>,
     Query(qs): Query<project::ProjectsQueryString>,
 ) -> impl IntoResponse {
@@ -54,10 +58,10 @@ async fn delegates_projects_handler(
                     }
 
                     let meta: project::Metadata = project.try_into().ok()?;
-                    let head =
-                        get_head_commit(&repo, &meta.urn, &meta.default_branch, &meta.delegates)
-                            .map(|h| h.id)
-                            .ok();
+                    let (head, head_peer) =
+                        get_head(&repo, &meta.urn, &meta.default_branch, &meta.delegates)
+                            .map(|(h, peer)| (Some(h.id), peer))
+                            .unwrap_or_default();
 
                     let issues = issues.count(&meta.urn).map_err(Error::Cobs).ok()?;
                     let patches = patches.count(&meta.urn).map_err(Error::Cobs).ok()?;
@@ -65,6 +69,7 @@ async fn delegates_projects_handler(
                     Some(Info {
                         meta,
                         head,
+                        head_peer,
                         issues,
                         patches,
                     })
@@ -77,8 +82,9 @@ async fn delegates_projects_handler(
         .take(per_page)
         .collect::<Result<Vec<_>, _>>()
         .map_err(Error::from)?;
+    let links = pagination_links(&uri, page, per_page, projects.len());
 
-    Ok::<_, Error>(Json(projects))
+    Ok::<_, Error>((links, Json(projects)))
 }
 
 #[cfg(test)]
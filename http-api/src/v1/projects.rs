@@ -1,12 +1,17 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, VecDeque};
 use std::convert::{TryFrom, TryInto};
+use std::ops::Bound;
 use std::str::FromStr;
+use std::sync::Arc;
 
+use axum::extract::OriginalUri;
 use axum::handler::Handler;
+use axum::headers::authorization::{Authorization, Bearer};
+use axum::headers::{ContentRange, IfNoneMatch, Range};
 use axum::http::{header, HeaderValue};
-use axum::response::IntoResponse;
-use axum::routing::get;
-use axum::{Extension, Json, Router};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Extension, Json, Router, TypedHeader};
 use hyper::StatusCode;
 use librad::identities::Project;
 use serde::Deserialize;
@@ -21,8 +26,8 @@ use librad::git::Urn;
 use librad::paths::Paths;
 use librad::PeerId;
 
-use radicle_common::cobs::{self, issue, patch, Store};
-use radicle_common::person;
+use radicle_common::cobs::{issue, patch, Store};
+use radicle_common::{keys, person};
 use radicle_source as source;
 use radicle_source::commit::Stats;
 use radicle_source::surf::vcs::git;
@@ -30,10 +35,20 @@ use radicle_surf::diff;
 
 use crate::axum_extra::{Path, Query};
 use crate::commit::{Commit, CommitContext, CommitTeaser, CommitsQueryString, Committer};
-use crate::project::{self, Info};
-use crate::{get_head_commit, Context, Error};
+use crate::project::{self, ActivityQueryString, Info};
+use crate::{get_head, get_head_commit, Context, Error};
+
+use super::pagination_links;
 
 const CACHE_1_HOUR: &str = "public, max-age=3600, must-revalidate";
+/// Maximum number of commit headers returned by a single history request.
+const MAX_COMMITS: usize = 1000;
+/// Maximum number of entries returned by a recursive tree request.
+const MAX_TREE_ENTRIES: usize = 10_000;
+/// Maximum number of branches checked for a commit, since each check walks history.
+const MAX_COMMIT_REFS: usize = 256;
+/// Maximum number of commits whose signer is checked when filtering history by author.
+const MAX_AUTHOR_SCAN: usize = 5000;
 
 pub fn router(ctx: Context) -> Router {
     Router::new()
@@ -41,6 +56,14 @@ pub fn router(ctx: Context) -> Router {
         .route("/projects/:project", get(project_alias_or_urn_handler))
         .route("/projects/:project/commits", get(history_handler))
         .route("/projects/:project/commits/:sha", get(commit_handler))
+        .route(
+            "/projects/:project/commits/:sha/diff",
+            get(commit_diff_handler),
+        )
+        .route(
+            "/projects/:project/commits/:sha/refs",
+            get(commit_refs_handler),
+        )
         .route(
             "/projects/:project/activity",
             get(
@@ -54,11 +77,14 @@ pub fn router(ctx: Context) -> Router {
         .route("/projects/:project/remotes", get(remotes_handler))
         .route("/projects/:project/remotes/:peer", get(remote_handler))
         .route("/projects/:project/blob/:sha/*path", get(blob_handler))
+        .route("/projects/:project/raw/:sha/*path", get(raw_handler))
+        .route("/projects/:project/readme", get(readme_head_handler))
         .route("/projects/:project/readme/:sha", get(readme_handler))
         .route("/projects/:project/patches", get(patches_handler))
         .route("/projects/:project/patches/:id", get(patch_handler))
         .route("/projects/:project/issues", get(issues_handler))
         .route("/projects/:project/issues/:id", get(issue_handler))
+        .route("/projects/:project/cache/purge", post(cache_purge_handler))
         .layer(Extension(ctx))
 }
 
@@ -68,22 +94,18 @@ fn get_project_info_sync(
     storage: deadpool::managed::Object<Storage, librad::git::storage::pool::InitError>,
 ) -> Result<Info, Error> {
     let repo = git2::Repository::open_bare(&ctx.paths.git_dir()).map_err(Error::from)?;
-    let whoami = person::local(&storage).map_err(Error::LocalIdentity)?;
-    let cobs = cobs::Store::new(whoami, &ctx.paths, &storage);
-    let issues = cobs.issues();
-    let patches = cobs.patches();
-
     let meta: project::Metadata = project.try_into()?;
-    let head = get_head_commit(&repo, &meta.urn, &meta.default_branch, &meta.delegates)
-        .map(|h| h.id)
-        .ok();
+    let (head, head_peer) = get_head(&repo, &meta.urn, &meta.default_branch, &meta.delegates)
+        .map(|(h, peer)| (Some(h.id), peer))
+        .unwrap_or_default();
 
-    let issues = issues.count(&meta.urn).map_err(Error::Cobs)?;
-    let patches = patches.count(&meta.urn).map_err(Error::Cobs)?;
+    // Counting collaborative objects is the expensive part, so counts are cached.
+    let (issues, patches) = ctx.project_counts(&meta.urn, head, &storage)?;
 
     let info = Info {
         meta,
         head,
+        head_peer,
         issues,
         patches,
     };
@@ -150,10 +172,15 @@ async fn get_projects_info(
 /// `GET /projects`
 async fn project_root_handler(
     Extension(ctx): Extension<Context>,
+    OriginalUri(uri): OriginalUri,
     Query(qs): Query<project::ProjectsQueryString>,
 ) -> impl IntoResponse {
+    let page = qs.page.unwrap_or(0);
+    let per_page = qs.per_page.unwrap_or(10);
     let projects = get_projects_info(ctx, Query(qs)).await?;
-    Ok::<_, Error>(Json(projects))
+    let links = pagination_links(&uri, page, per_page, projects.len());
+
+    Ok::<_, Error>((links, Json(projects)))
 }
 
 /// Get project commit.
@@ -162,37 +189,160 @@ async fn commit_handler(
     Extension(ctx): Extension<Context>,
     Path((project, sha)): Path<(Urn, One)>,
 ) -> impl IntoResponse {
-    let reference = Reference::head(Namespace::from(project.clone()), None, sha.to_owned());
-    let commit = browse(reference, ctx.paths.clone(), |browser| {
-        let oid = browser.oid(&sha)?;
-        radicle_source::commit(browser, oid)
-    })
-    .await?;
-
-    let projects = ctx.projects.read().await;
-    let fingerprints = projects.get(&project);
-    let fp = ctx.commit_ssh_fingerprint(&commit.header.sha1.to_string())?;
-    let committer = if let (Some(fps), Some(fp)) = (fingerprints, fp) {
-        fps.get(&fp).cloned().map(|peer| Committer { peer })
+    // Only full commit hashes are cached, since they always refer to the same commit.
+    let cache_key = is_oid(sha.as_str()).then(|| sha.to_string());
+    let cached = cache_key
+        .as_ref()
+        .and_then(|key| ctx.commit_cache.get(&project, key));
+    let commit = if let Some(commit) = cached {
+        commit
     } else {
-        None
+        let reference = Reference::head(Namespace::from(project.clone()), None, sha.to_owned());
+        let commit = browse(reference, &ctx, move |browser| {
+            let oid = browser.oid(&sha)?;
+            radicle_source::commit(browser, oid)
+        })
+        .await
+        .map(Arc::new)?;
+
+        if let Some(key) = &cache_key {
+            ctx.commit_cache.insert(&project, key, commit.clone());
+        }
+        commit
     };
 
+    let committer = ctx
+        .commit_signers(&project, &[commit.header.sha1.to_string()])
+        .await?
+        .pop()
+        .flatten()
+        .map(|peer| Committer { peer });
+
     let response = Commit {
-        header: commit.header,
-        diff: commit.diff,
-        stats: commit.stats,
-        branches: commit.branches,
+        header: commit.header.clone(),
+        diff: commit.diff.clone(),
+        stats: commit.stats.clone(),
+        branches: commit.branches.clone(),
         context: CommitContext { committer },
     };
 
     Ok::<_, Error>(Json(json!(response)))
 }
 
+/// Get project commit diff, without the rest of the commit details.
+/// `GET /projects/:project/commits/:sha/diff`
+async fn commit_diff_handler(
+    Extension(ctx): Extension<Context>,
+    Path((project, sha)): Path<(Urn, One)>,
+) -> impl IntoResponse {
+    let oid = git2::Oid::from_str(sha.as_str()).map_err(|_| Error::NotFound)?;
+    let repo = git2::Repository::open_bare(ctx.paths.git_dir()).map_err(Error::from)?;
+    let parent = repo
+        .find_commit(oid)
+        .map_err(|_| Error::NotFound)?
+        .parent_ids()
+        .next();
+
+    let reference = Reference::head(Namespace::from(project), None, sha);
+    let diff = browse(reference, &ctx, move |browser| match parent {
+        Some(parent) => browser
+            .diff(parent, oid)
+            .map_err(radicle_source::Error::from),
+        // The initial commit has nothing to diff against.
+        None => radicle_source::commit(browser, oid).map(|commit| commit.diff),
+    })
+    .await?;
+
+    let response = json!({
+        "stats": Changeset::stats(&diff),
+        "diff": diff,
+    });
+
+    Ok::<_, Error>(Json(response))
+}
+
+/// Get the branches that contain a project commit, ie. whose head is the commit or one of
+/// its descendants, across the local branches and those of all remotes.
+/// `GET /projects/:project/commits/:sha/refs`
+///
+/// At most [`MAX_COMMIT_REFS`] branches are checked, in which case `truncated` is set.
+async fn commit_refs_handler(
+    Extension(ctx): Extension<Context>,
+    Path((project, sha)): Path<(Urn, One)>,
+) -> impl IntoResponse {
+    let oid = git2::Oid::from_str(sha.as_str()).map_err(|_| Error::NotFound)?;
+    let paths = ctx.paths.clone();
+    let (refs, truncated) = blocking(&ctx, move || commit_refs(&paths, &project, oid)).await?;
+
+    Ok::<_, Error>(Json(json!({ "refs": refs, "truncated": truncated })))
+}
+
+/// List the branches of a project that contain the given commit, checking at most
+/// [`MAX_COMMIT_REFS`] of them. Returns the branches and whether the list was truncated.
+fn commit_refs(
+    paths: &Paths,
+    project: &Urn,
+    oid: git2::Oid,
+) -> Result<(Vec<serde_json::Value>, bool), Error> {
+    let repo = git2::Repository::open_bare(paths.git_dir()).map_err(Error::from)?;
+    repo.find_commit(oid).map_err(|_| Error::NotFound)?;
+
+    // This is necessary to get any references to show up in the later calls.
+    let _ = repo.references().map_err(Error::from)?;
+
+    let prefix = format!("refs/namespaces/{}/refs/", project.encode_id());
+    let glob = format!("{}*", prefix);
+    let mut refs = Vec::new();
+    let mut truncated = false;
+    let mut checked = 0;
+
+    for reference in repo.references_glob(&glob).map_err(Error::from)? {
+        let reference = reference.map_err(Error::from)?;
+        let (name, head) = match (reference.name(), reference.target()) {
+            (Some(name), Some(head)) => (name, head),
+            _ => continue,
+        };
+        let name = name.strip_prefix(&prefix).unwrap_or(name);
+        // Eg. `heads/master` or `remotes/<peer>/heads/master`.
+        let (peer, branch) = match name.strip_prefix("remotes/") {
+            Some(rest) => match rest.split_once("/heads/") {
+                Some((peer, branch)) => (Some(peer), branch),
+                None => continue,
+            },
+            None => match name.strip_prefix("heads/") {
+                Some(branch) => (None, branch),
+                None => continue,
+            },
+        };
+
+        if checked == MAX_COMMIT_REFS {
+            truncated = true;
+            break;
+        }
+        checked += 1;
+
+        if head == oid || repo.graph_descendant_of(head, oid).map_err(Error::from)? {
+            refs.push(json!({
+                "peer": peer,
+                "branch": branch,
+                "head": head.to_string(),
+            }));
+        }
+    }
+
+    Ok((refs, truncated))
+}
+
 /// Get project commit range.
 /// `GET /projects/:project/commits?from=<sha>`
+///
+/// With `?stats=true`, the additions and deletions of each returned commit are included.
+///
+/// With `?author=<peer|fingerprint>`, only the commits signed by the author are returned.
+/// Since only part of the history is scanned for them, `total` is omitted in that case.
 async fn history_handler(
     Extension(ctx): Extension<Context>,
+    OriginalUri(uri): OriginalUri,
     Path(project): Path<Urn>,
     Query(qs): Query<CommitsQueryString>,
 ) -> impl IntoResponse {
@@ -202,7 +352,10 @@ async fn history_handler(
         parent,
         page,
         per_page,
+        max,
+        author,
         verified,
+        stats,
     } = qs;
 
     let (sha, fallback_to_head) = match parent {
@@ -218,16 +371,30 @@ async fn history_handler(
         }
     };
 
-    let reference = Reference::head(
-        Namespace::from(project.to_owned()),
-        None,
-        One::from_str(&sha).map_err(|_| Error::NotFound)?,
-    );
+    let cached = if is_oid(&sha) {
+        ctx.history_cache.get(&project, &sha)
+    } else {
+        None
+    };
+    let commits = if let Some(commits) = cached {
+        commits
+    } else {
+        let reference = Reference::head(
+            Namespace::from(project.to_owned()),
+            None,
+            One::from_str(&sha).map_err(|_| Error::NotFound)?,
+        );
+        let commits = browse(reference, &ctx, |browser| {
+            radicle_source::commits::<PeerId>(browser, None)
+        })
+        .await
+        .map(Arc::new)?;
 
-    let commits = browse(reference, ctx.paths.to_owned(), |browser| {
-        radicle_source::commits::<PeerId>(browser, None)
-    })
-    .await?;
+        if is_oid(&sha) {
+            ctx.history_cache.insert(&project, &sha, commits.clone());
+        }
+        commits
+    };
 
     // If a pagination is defined, we do not want to paginate the commits, and we return all of them on the first page.
     let page = page.unwrap_or(0);
@@ -236,9 +403,9 @@ async fn history_handler(
     } else {
         per_page.unwrap_or(30)
     };
+    // Whatever the time window, never return more than `max` headers.
+    let per_page = per_page.min(max.unwrap_or(MAX_COMMITS).min(MAX_COMMITS));
 
-    let projects = ctx.projects.read().await;
-    let fingerprints = projects.get(&project);
     let headers = commits
         .headers
         .iter()
@@ -254,44 +421,173 @@ async fn history_handler(
                 true
             }
         })
+        .collect::<Vec<_>>();
+    let filtered = author.is_some();
+    let headers = if let Some(author) = author {
+        let limit = page.saturating_add(1).saturating_mul(per_page);
+        commits_by_author(&ctx, &project, &author, headers, limit).await?
+    } else {
+        headers
+    };
+    let total = headers.len();
+    let headers = headers
+        .into_iter()
         .skip(page * per_page)
         .take(per_page)
-        .map(|header| {
-            let committer = if verified.unwrap_or_default() {
-                let fp = ctx.commit_ssh_fingerprint(&header.sha1.to_string())?;
-                if let (Some(fps), Some(fp)) = (fingerprints, fp) {
-                    fps.get(&fp).cloned().map(|peer| Committer { peer })
-                } else {
-                    None
-                }
-            } else {
-                None
-            };
-
-            Ok(CommitTeaser {
-                header: header.clone(),
-                context: CommitContext { committer },
-            })
+        .collect::<Vec<_>>();
+    let signers = if verified.unwrap_or_default() {
+        let shas = headers
+            .iter()
+            .map(|header| header.sha1.to_string())
+            .collect::<Vec<_>>();
+        ctx.commit_signers(&project, &shas).await?
+    } else {
+        vec![None; headers.len()]
+    };
+    let stats = if stats.unwrap_or_default() {
+        commits_stats(&ctx, &project, &sha, &headers)
+            .await?
+            .into_iter()
+            .map(Some)
+            .collect()
+    } else {
+        vec![None; headers.len()]
+    };
+    let headers = headers
+        .into_iter()
+        .zip(signers)
+        .zip(stats)
+        .map(|((header, signer), stats)| CommitTeaser {
+            header: header.clone(),
+            context: CommitContext {
+                committer: signer.map(|peer| Committer { peer }),
+            },
+            stats,
         })
-        .collect::<Result<Vec<CommitTeaser>, Error>>()?;
+        .collect::<Vec<_>>();
 
-    let response = json!({
+    let mut response = json!({
         "headers": &headers,
         "stats": &commits.stats,
     });
+    if !filtered {
+        response["total"] = json!(total);
+    }
+    let links = pagination_links(&uri, page, per_page, headers.len());
 
     if fallback_to_head {
-        return Ok::<_, Error>((StatusCode::FOUND, Json(response)));
+        return Ok::<_, Error>((StatusCode::FOUND, links, Json(response)));
+    }
+
+    Ok::<_, Error>((StatusCode::OK, links, Json(response)))
+}
+
+/// Compute the change stats of the given commits against their first parent, browsing from
+/// the given revision.
+async fn commits_stats(
+    ctx: &Context,
+    project: &Urn,
+    revision: &str,
+    headers: &[&source::commit::Header],
+) -> Result<Vec<Stats>, Error> {
+    let repo = git2::Repository::open_bare(ctx.paths.git_dir()).map_err(Error::from)?;
+    let commits = headers
+        .iter()
+        .map(|header| {
+            let oid = git2::Oid::from_str(&header.sha1.to_string()).map_err(Error::from)?;
+            let parent = repo
+                .find_commit(oid)
+                .map_err(Error::from)?
+                .parent_ids()
+                .next();
+
+            Ok((oid, parent))
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let reference = Reference::head(
+        Namespace::from(project.to_owned()),
+        None,
+        One::from_str(revision).map_err(|_| Error::NotFound)?,
+    );
+
+    browse(reference, ctx, move |browser| {
+        commits
+            .into_iter()
+            .map(|(oid, parent)| {
+                let diff = match parent {
+                    Some(parent) => browser.diff(parent, oid)?,
+                    // The initial commit has nothing to diff against.
+                    None => radicle_source::commit(browser, oid)?.diff,
+                };
+                Ok(Changeset::stats(&diff))
+            })
+            .collect()
+    })
+    .await
+}
+
+/// Keep only the commits signed by the given author, which is either a peer id or an SSH
+/// key fingerprint. Authors that aren't tracked for the project have no commits.
+///
+/// Since finding the signer of a commit is costly, the commits are scanned on the blocking
+/// thread pool, and scanning stops after `limit` matches or [`MAX_AUTHOR_SCAN`] commits.
+async fn commits_by_author<'a>(
+    ctx: &Context,
+    project: &Urn,
+    author: &str,
+    headers: Vec<&'a source::commit::Header>,
+    limit: usize,
+) -> Result<Vec<&'a source::commit::Header>, Error> {
+    let fp = match PeerId::from_str(author) {
+        Ok(peer) => keys::to_ssh_fingerprint(&peer).expect("Conversion cannot fail"),
+        Err(_) => author.to_owned(),
+    };
+    let is_tracked = |projects: &HashMap<Urn, crate::Fingerprints>| {
+        projects
+            .get(project)
+            .map_or(false, |fps| fps.contains_key(&fp))
+    };
+
+    if !is_tracked(&*ctx.projects.read().await) {
+        // The author may have been tracked since the last refresh.
+        ctx.populate_fingerprints_for(project).await?;
+
+        if !is_tracked(&*ctx.projects.read().await) {
+            return Ok(vec![]);
+        }
     }
 
-    Ok::<_, Error>((StatusCode::OK, Json(response)))
+    let shas = headers
+        .iter()
+        .map(|header| header.sha1.to_string())
+        .collect::<Vec<_>>();
+    let scan = ctx.clone();
+    let matches = blocking(ctx, move || {
+        let mut matches = Vec::new();
+
+        for (i, sha) in shas.iter().take(MAX_AUTHOR_SCAN).enumerate() {
+            if matches.len() >= limit {
+                break;
+            }
+            if scan.commit_ssh_fingerprint(sha)?.as_ref() == Some(&fp) {
+                matches.push(i);
+            }
+        }
+        Ok(matches)
+    })
+    .await?;
+
+    Ok(matches.into_iter().map(|i| headers[i]).collect())
 }
 
 /// Get project activity for the past year.
-/// `GET /projects/:project/activity`
+/// `GET /projects/:project/activity?format=<unix|iso>&bucket=<day|week>`
+///
+/// Returns commit timestamps, or with `bucket`, the number of commits per day or week.
 async fn activity_handler(
     Extension(ctx): Extension<Context>,
     Path(project): Path<Urn>,
+    Query(qs): Query<ActivityQueryString>,
 ) -> impl IntoResponse {
     let info = ctx.project_info(project.to_owned()).await?;
 
@@ -310,7 +606,7 @@ async fn activity_handler(
     let current_date = chrono::Utc::now().timestamp();
     let one_year_ago = chrono::Duration::weeks(52);
 
-    let timestamps = browse(reference, ctx.paths.to_owned(), |browser| {
+    let timestamps = browse(reference, &ctx, move |browser| {
         let activity = browser
             .get()
             .iter()
@@ -328,7 +624,24 @@ async fn activity_handler(
     })
     .await?;
 
-    Ok::<_, Error>((StatusCode::OK, Json(json!({ "activity": timestamps }))))
+    let format = qs.format.unwrap_or_default();
+    let activity = if let Some(bucket) = qs.bucket {
+        let mut counts = BTreeMap::new();
+        for seconds in timestamps {
+            *counts.entry(bucket.start(seconds)).or_insert(0) += 1;
+        }
+        counts
+            .into_iter()
+            .map(|(start, count)| json!({ "date": format.format(start), "count": count }))
+            .collect::<Vec<_>>()
+    } else {
+        timestamps
+            .into_iter()
+            .map(|seconds| format.format(seconds))
+            .collect()
+    };
+
+    Ok::<_, Error>((StatusCode::OK, Json(json!({ "activity": activity }))))
 }
 
 /// Get project metadata.
@@ -336,64 +649,160 @@ async fn activity_handler(
 async fn project_alias_or_urn_handler(
     Extension(ctx): Extension<Context>,
     Path(urn_or_alias): Path<String>,
+    if_none_match: Option<TypedHeader<IfNoneMatch>>,
 ) -> impl IntoResponse {
     let urn = Urn::from_str(&urn_or_alias);
     let urn = if let Ok(urn) = urn {
         urn
     } else {
-        let alias = urn_or_alias;
-        let mut aliases = ctx.aliases.write().await;
-        if !aliases.contains_key(&alias) {
-            // If the alias does not exist, rebuild the cache.
-            ctx.populate_aliases(&mut aliases).await?;
-        }
-
-        aliases.get(&alias).cloned().ok_or(Error::NotFound)?
+        ctx.resolve_alias(&urn_or_alias).await?
     };
 
+    if let Some(TypedHeader(if_none_match)) = if_none_match {
+        if let Some(etag) = ctx.project_etag(&urn).await? {
+            if !if_none_match.precondition_passes(&etag) {
+                return Ok((StatusCode::NOT_MODIFIED, TypedHeader(etag)).into_response());
+            }
+        }
+    }
+
     let info = ctx.project_info(urn).await?;
-    Ok::<_, Error>(Json(info))
+    Ok::<_, Error>((TypedHeader(info.etag()), Json(info)).into_response())
+}
+
+#[derive(Deserialize, Default)]
+struct TreeQuery {
+    recursive: bool,
 }
 
 /// Get project source tree.
-/// `GET /projects/:project/tree/:sha/*path`
+/// `GET /projects/:project/tree/:sha/*path?recursive=<bool>`
+///
+/// Instead of a commit SHA, a branch name may be given, eg. `/tree/master/`.
+///
+/// With `?recursive=true`, the entries of all sub-directories are included as well, up to
+/// [`MAX_TREE_ENTRIES`] entries, in which case `truncated` is set. These entries only have a
+/// `path`, `name`, `kind` and `oid`, since finding the last commit of each would walk history.
 async fn tree_handler(
     Extension(ctx): Extension<Context>,
     Path((project, sha, path)): Path<(Urn, One, String)>,
+    query: Option<Query<TreeQuery>>,
 ) -> impl IntoResponse {
     let path = path.strip_prefix('/').ok_or(Error::NotFound)?.to_string();
-    let reference = Reference::head(Namespace::from(project), None, sha);
-    let (tree, stats) = browse(reference, ctx.paths, |browser| {
-        Ok((
-            radicle_source::tree::<PeerId>(browser, None, Some(path))?,
-            browser.get_stats()?,
-        ))
+    let Query(query) = query.unwrap_or_default();
+    let sha = resolve_revision(&ctx, &project, sha).await?;
+    let reference = Reference::head(Namespace::from(project), None, sha.clone());
+    let tree_path = path.clone();
+    let (tree, stats) = browse(reference, &ctx, move |browser| {
+        let tree = radicle_source::tree::<PeerId>(browser, None, Some(tree_path))?;
+
+        Ok((tree, browser.get_stats()?))
     })
     .await?;
-    let response = json!({
+    let mut response = json!({
         "path": &tree.path,
         "entries": &tree.entries,
         "info": &tree.info,
         "stats": &stats,
     });
+    if query.recursive {
+        let paths = ctx.paths.clone();
+        let (entries, truncated) = blocking(&ctx, move || {
+            tree_entries_recursive(&paths, sha.as_str(), &path)
+        })
+        .await?;
+
+        response["entries"] = json!(entries);
+        response["truncated"] = json!(truncated);
+    }
 
     Ok::<_, Error>(Json(response))
 }
 
+/// List the entries of the tree at the given path of a commit, and of its sub-directories,
+/// breadth-first. Returns whether the listing was truncated to [`MAX_TREE_ENTRIES`].
+fn tree_entries_recursive(
+    paths: &Paths,
+    sha: &str,
+    path: &str,
+) -> Result<(Vec<serde_json::Value>, bool), Error> {
+    let repo = git2::Repository::open_bare(paths.git_dir())?;
+    let tree = repo
+        .revparse_single(sha)
+        .and_then(|object| object.peel_to_tree())
+        .map_err(|_| Error::NotFound)?;
+    let dir = path.trim_end_matches('/');
+    let tree = if dir.is_empty() {
+        tree
+    } else {
+        tree.get_path(std::path::Path::new(dir))
+            .and_then(|entry| entry.to_object(&repo))
+            .and_then(|object| object.peel_to_tree())
+            .map_err(|_| Error::NotFound)?
+    };
+    let mut queue = VecDeque::from(vec![(dir.to_owned(), tree.id())]);
+    let mut result = Vec::new();
+
+    while let Some((dir, oid)) = queue.pop_front() {
+        for entry in repo.find_tree(oid)?.iter() {
+            if result.len() == MAX_TREE_ENTRIES {
+                return Ok((result, true));
+            }
+            let name = String::from_utf8_lossy(entry.name_bytes()).into_owned();
+            let path = if dir.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", dir, name)
+            };
+            let kind = entry.kind();
+
+            if kind == Some(git2::ObjectType::Tree) {
+                queue.push_back((path.clone(), entry.id()));
+            }
+            result.push(json!({
+                "path": path,
+                "name": name,
+                "kind": kind.map_or("unknown", |k| k.str()),
+                "oid": entry.id().to_string(),
+            }));
+        }
+    }
+
+    Ok((result, false))
+}
+
 /// Get all project remotes.
 /// `GET /projects/:project/remotes`
+///
+/// With `?heads=true`, the branch heads of each remote are included, as returned by
+/// `GET /projects/:project/remotes/:peer`.
 async fn remotes_handler(
     Extension(ctx): Extension<Context>,
     Path(urn): Path<Urn>,
+    query: Option<Query<RemotesQuery>>,
 ) -> impl IntoResponse {
+    let Query(query) = query.unwrap_or_default();
     let storage = ctx.storage().await?;
     let project = identities::project::get(storage.read_only(), &urn)
         .map_err(Error::Identities)?
         .ok_or(Error::NotFound)?;
     let meta: project::Metadata = project.try_into().map_err(Error::Project)?;
-    let response = project::tracked(&meta, storage.read_only())?;
+    let tracked = project::tracked(&meta, storage.read_only())?;
 
-    Ok::<_, Error>(Json(response))
+    if !query.heads {
+        return Ok::<_, Error>(Json(json!(tracked)));
+    }
+    let repo = git2::Repository::open_bare(ctx.paths.git_dir()).map_err(Error::from)?;
+    let remotes = tracked
+        .into_iter()
+        .map(|info| {
+            let heads = remote_heads(&repo, &urn, &info.id)?;
+
+            Ok(Remote { info, heads })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    Ok::<_, Error>(Json(json!(remotes)))
 }
 
 /// Get project remote.
@@ -403,6 +812,31 @@ async fn remote_handler(
     Path((project, peer_id)): Path<(Urn, PeerId)>,
 ) -> impl IntoResponse {
     let repo = git2::Repository::open_bare(ctx.paths.git_dir()).map_err(Error::from)?;
+    let branches = remote_heads(&repo, &project, &peer_id)?;
+    let response = json!({ "heads": &branches });
+
+    Ok::<_, Error>(Json(response))
+}
+
+#[derive(Deserialize, Default)]
+struct RemotesQuery {
+    heads: bool,
+}
+
+/// A tracked remote, along with its branch heads.
+#[derive(serde::Serialize)]
+struct Remote {
+    #[serde(flatten)]
+    info: project::PeerInfo,
+    heads: HashMap<String, String>,
+}
+
+/// Get the branch heads of a project remote, by branch name.
+fn remote_heads(
+    repo: &git2::Repository,
+    project: &Urn,
+    peer_id: &PeerId,
+) -> Result<HashMap<String, String>, Error> {
     // This is necessary to get any references to show up in the later calls. Go figure.
     let _ = repo.references().map_err(Error::from)?;
 
@@ -413,7 +847,7 @@ async fn remote_handler(
         namespace, remote
     );
     let glob = format!("{}*", prefix);
-    let refs = repo.references_glob(&glob).unwrap();
+    let refs = repo.references_glob(&glob).map_err(Error::from)?;
 
     let branches = refs
         .filter_map(|r| {
@@ -425,9 +859,7 @@ async fn remote_handler(
         })
         .collect::<HashMap<_, _>>();
 
-    let response = json!({ "heads": &branches });
-
-    Ok::<_, Error>(Json(response))
+    Ok(branches)
 }
 
 #[derive(Deserialize, Default)]
@@ -438,6 +870,9 @@ struct BlobQuery {
 
 /// Get project source file.
 /// `GET /projects/:project/blob/:sha/*path?highlight=<bool>`
+///
+/// Binary files are never highlighted: their content is returned base64-encoded, with
+/// `binary` set.
 async fn blob_handler(
     Extension(ctx): Extension<Context>,
     Path((project, sha, path)): Path<(Urn, One, String)>,
@@ -446,35 +881,202 @@ async fn blob_handler(
     let path = path.strip_prefix('/').ok_or(Error::NotFound)?.to_string();
     let Query(query) = query.unwrap_or_default();
     let theme = if query.highlight {
-        Some(query.theme.as_deref().unwrap_or(ctx.theme.as_str()))
+        Some(query.theme.unwrap_or_else(|| ctx.theme.clone()))
     } else {
         None
     };
     let reference = Reference::head(Namespace::from(project), None, sha);
-    let blob = browse(reference, ctx.paths, |browser| {
-        radicle_source::blob::highlighting::blob::<PeerId>(browser, None, path.as_str(), theme)
+    let blob = browse(reference, &ctx, move |browser| {
+        radicle_source::blob::highlighting::blob::<PeerId>(
+            browser,
+            None,
+            path.as_str(),
+            theme.as_deref(),
+        )
     })
     .await?;
+    // Only text content is highlighted, so binary files come back with their raw bytes.
+    let content = match &blob.content {
+        source::blob::BlobContent::Binary(bytes) => Some(base64::encode(bytes)),
+        _ => None,
+    };
+    let mut blob = json!(blob);
+
+    if let Some(content) = content {
+        blob["binary"] = json!(true);
+        blob["content"] = json!(content);
+    }
 
     Ok::<_, Error>(Json(blob))
 }
 
+/// Get project source file as raw bytes.
+/// `GET /projects/:project/raw/:sha/*path`
+///
+/// Honors the `Range` header, in which case only the first requested byte range is returned.
+async fn raw_handler(
+    Extension(ctx): Extension<Context>,
+    Path((project, sha, path)): Path<(Urn, One, String)>,
+    range: Option<TypedHeader<Range>>,
+) -> impl IntoResponse {
+    let path = path.strip_prefix('/').ok_or(Error::NotFound)?.to_string();
+    let sha = resolve_revision(&ctx, &project, sha).await?;
+    let oid = git2::Oid::from_str(sha.as_str()).map_err(|_| Error::NotFound)?;
+    let (content, binary) = {
+        let paths = ctx.paths.clone();
+        let path = path.clone();
+
+        blocking(&ctx, move || blob_content(&paths, &project, oid, &path)).await?
+    };
+
+    let content_type = raw_content_type(&path, binary);
+    let length = content.len() as u64;
+    let bounds = range.and_then(|TypedHeader(range)| range.iter().next());
+
+    let response = match bounds {
+        None => (
+            [
+                (header::CONTENT_TYPE, content_type),
+                (header::ACCEPT_RANGES, "bytes"),
+            ],
+            content,
+        )
+            .into_response(),
+        Some(bounds) => match byte_range(bounds, length) {
+            Some((start, end)) => (
+                StatusCode::PARTIAL_CONTENT,
+                [
+                    (header::CONTENT_TYPE, content_type),
+                    (header::ACCEPT_RANGES, "bytes"),
+                ],
+                TypedHeader(ContentRange::bytes(start..=end, length).expect("Range is bounded")),
+                content[start as usize..=end as usize].to_vec(),
+            )
+                .into_response(),
+            None => (
+                StatusCode::RANGE_NOT_SATISFIABLE,
+                TypedHeader(ContentRange::unsatisfied_bytes(length)),
+            )
+                .into_response(),
+        },
+    };
+
+    Ok::<Response, Error>(response)
+}
+
+/// Get the content of a file at the given commit of a project, and whether it is binary.
+///
+/// The commit must be reachable from one of the project's refs, so that files of other
+/// projects can't be read through it.
+fn blob_content(
+    paths: &Paths,
+    project: &Urn,
+    oid: git2::Oid,
+    path: &str,
+) -> Result<(Vec<u8>, bool), Error> {
+    let repo = git2::Repository::open_bare(paths.git_dir())?;
+    let mut walk = repo.revwalk()?;
+    walk.push_glob(&format!("refs/namespaces/{}/refs/*", project.encode_id()))?;
+
+    if !walk.any(|id| matches!(id, Ok(id) if id == oid)) {
+        return Err(Error::NotFound);
+    }
+    let commit = repo.find_commit(oid).map_err(|_| Error::NotFound)?;
+    let entry = commit
+        .tree()?
+        .get_path(std::path::Path::new(path))
+        .map_err(|_| Error::NotFound)?;
+    let blob = entry
+        .to_object(&repo)?
+        .into_blob()
+        .map_err(|_| Error::NotFound)?;
+
+    Ok((blob.content().to_vec(), blob.is_binary()))
+}
+
+/// Resolve a byte range against a blob of the given length, returning the
+/// inclusive start and end offsets, or `None` if the range can't be satisfied.
+fn byte_range(bounds: (Bound<u64>, Bound<u64>), length: u64) -> Option<(u64, u64)> {
+    if length == 0 {
+        return None;
+    }
+    let (start, end) = match bounds {
+        (Bound::Included(start), Bound::Included(end)) => (start, end.min(length - 1)),
+        (Bound::Included(start), Bound::Unbounded) => (start, length - 1),
+        // A suffix range, eg. `bytes=-500` returns the last 500 bytes.
+        (Bound::Unbounded, Bound::Included(suffix)) if suffix > 0 => {
+            (length.saturating_sub(suffix), length - 1)
+        }
+        _ => return None,
+    };
+
+    if start > end {
+        return None;
+    }
+    Some((start, end))
+}
+
+/// Guess the content type of a raw file from its extension.
+fn raw_content_type(path: &str, binary: bool) -> &'static str {
+    let extension = std::path::Path::new(path)
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("png") => "image/png",
+        Some("jpg" | "jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("pdf") => "application/pdf",
+        Some("mp3") => "audio/mpeg",
+        Some("ogg") => "audio/ogg",
+        Some("wav") => "audio/wav",
+        Some("mp4") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("json") => "application/json",
+        _ if binary => "application/octet-stream",
+        // We never serve text as HTML, to avoid having it interpreted by browsers. This includes
+        // SVG images, which may embed scripts.
+        _ => "text/plain; charset=utf-8",
+    }
+}
+
 /// Get project readme.
 /// `GET /projects/:project/readme/:sha`
 async fn readme_handler(
     Extension(ctx): Extension<Context>,
     Path((project, sha)): Path<(Urn, One)>,
 ) -> impl IntoResponse {
+    let blob = readme(&ctx, project, sha).await?;
+
+    Ok::<_, Error>(Json(blob))
+}
+
+/// Get project readme, at the project head.
+/// `GET /projects/:project/readme`
+async fn readme_head_handler(
+    Extension(ctx): Extension<Context>,
+    Path(project): Path<Urn>,
+) -> impl IntoResponse {
+    let info = ctx.project_info(project.to_owned()).await?;
+    let head = if let Some(head) = info.head {
+        One::from_str(&head.to_string()).map_err(|_| Error::NotFound)?
+    } else {
+        return Err(Error::NoHead("project head is not set"));
+    };
+    let blob = readme(&ctx, project, head).await?;
+
+    Ok::<_, Error>(Json(blob))
+}
+
+/// Find the readme of a project at the given commit, trying each configured readme file name.
+async fn readme(ctx: &Context, project: Urn, sha: One) -> Result<source::Blob, Error> {
     let reference = Reference::head(Namespace::from(project), None, sha);
-    let paths = &[
-        "README",
-        "README.md",
-        "README.markdown",
-        "README.txt",
-        "README.rst",
-        "Readme.md",
-    ];
-    let blob = browse(reference, ctx.paths, |browser| {
+    let paths = ctx.readmes.clone();
+
+    browse(reference, ctx, move |browser| {
         for path in paths {
             if let Ok(blob) =
                 radicle_source::blob::highlighting::blob::<PeerId>(browser, None, path, None)
@@ -487,9 +1089,7 @@ async fn readme_handler(
             Path::try_from("README").unwrap(),
         ))
     })
-    .await?;
-
-    Ok::<_, Error>(Json(blob))
+    .await
 }
 
 async fn patch_handler(
@@ -532,17 +1132,44 @@ async fn patch_handler(
 
 /// Get project patches list.
 /// `GET /projects/:project/patches`
+///
+/// Patches may be filtered by state, eg. `?state=merged`, and are sorted by creation time,
+/// newest first, unless eg. `?sort=updated&order=asc` is given.
 async fn patches_handler(
     Extension(ctx): Extension<Context>,
     Path(urn): Path<Urn>,
+    Query(qs): Query<project::PatchesQueryString>,
 ) -> impl IntoResponse {
     let storage = ctx.storage().await?;
     let whoami = person::local(&*storage).map_err(Error::LocalIdentity)?;
     let store = Store::new(whoami, &ctx.paths, &storage);
     let patches = patch::PatchStore::new(&store);
-    let all: Vec<_> = patches
+    let mut all: Vec<_> = patches
         .all(&urn)
         .map_err(Error::Cobs)?
+        .into_iter()
+        .filter(|(_, patch)| qs.state.map_or(true, |s| s.matches(patch)))
+        .collect();
+
+    let order = qs.order.unwrap_or(project::Order::Desc);
+    match qs.sort.unwrap_or(project::Sort::Created) {
+        project::Sort::Created => order.sort(&mut all, |(_, patch)| patch.timestamp),
+        project::Sort::Updated => order.sort(&mut all, |(_, patch)| {
+            patch
+                .revisions
+                .iter()
+                .flat_map(|r| {
+                    r.merges
+                        .iter()
+                        .map(|m| m.timestamp)
+                        .chain(Some(r.timestamp))
+                })
+                .chain(Some(patch.timestamp))
+                .max()
+        }),
+    }
+
+    let all: Vec<_> = all
         .into_iter()
         .map(|(id, mut patch)| {
             if let Err(e) = patch
@@ -561,18 +1188,40 @@ async fn patches_handler(
 
 /// Get project issues list.
 /// `GET /projects/:project/issues`
+///
+/// Issues may be filtered by state, eg. `?state=open`, and are sorted by creation time,
+/// newest first, unless eg. `?sort=updated&order=asc` is given.
 async fn issues_handler(
     Extension(ctx): Extension<Context>,
     Path(project): Path<Urn>,
+    Query(qs): Query<project::IssuesQueryString>,
 ) -> impl IntoResponse {
     // TODO: Handle non-existing project.
     let storage = ctx.storage().await?;
     let whoami = person::local(&*storage).map_err(Error::LocalIdentity)?;
     let store = Store::new(whoami, &ctx.paths, &storage);
     let issues = issue::IssueStore::new(&store);
-    let all: Vec<_> = issues
+    let mut all: Vec<_> = issues
         .all(&project)
         .map_err(Error::Cobs)?
+        .into_iter()
+        .filter(|(_, issue)| qs.state.map_or(true, |s| s.matches(issue)))
+        .collect();
+
+    let order = qs.order.unwrap_or(project::Order::Desc);
+    match qs.sort.unwrap_or(project::Sort::Created) {
+        project::Sort::Created => order.sort(&mut all, |(_, issue)| issue.timestamp),
+        project::Sort::Updated => order.sort(&mut all, |(_, issue)| {
+            issue
+                .discussion
+                .iter()
+                .map(|c| c.timestamp)
+                .chain(Some(issue.timestamp))
+                .max()
+        }),
+    }
+
+    let all: Vec<_> = all
         .into_iter()
         .map(|(id, mut issue)| {
             if let Err(e) = issue
@@ -614,9 +1263,52 @@ async fn issue_handler(
     Ok::<_, Error>(Json(Cob::new(issue_id, issue)))
 }
 
-async fn browse<T, F>(reference: Reference<Single>, paths: Paths, callback: F) -> Result<T, Error>
+/// Purge the cached responses of a project. Requires a signed-in session.
+/// `POST /projects/:project/cache/purge`
+///
+/// Sessions are tied to an Ethereum address rather than to a peer, so any signed-in session
+/// can purge the cache of any project. This is harmless: purged responses are only computed
+/// again on the next request.
+async fn cache_purge_handler(
+    Extension(ctx): Extension<Context>,
+    auth: Option<TypedHeader<Authorization<Bearer>>>,
+    Path(project): Path<Urn>,
+) -> impl IntoResponse {
+    let TypedHeader(auth) = auth.ok_or(Error::Auth("Missing session token"))?;
+    ctx.authorize(auth.token()).await?;
+
+    let purged = ctx.purge_cache(&project);
+
+    Ok::<_, Error>(Json(json!({ "purged": purged })))
+}
+
+/// Resolve a revision, which is either a commit SHA or a branch name, to a commit SHA.
+///
+/// Branch names are resolved against the project delegates, the same way the project head is.
+/// Since a branch name may look like a short commit hash, those are only tried when no branch
+/// matches.
+async fn resolve_revision(ctx: &Context, urn: &Urn, revision: One) -> Result<One, Error> {
+    if is_oid(revision.as_str()) {
+        return Ok(revision);
+    }
+
+    let storage = ctx.storage().await?;
+    let project = identities::project::get(storage.read_only(), urn)
+        .map_err(Error::Identities)?
+        .ok_or(Error::NotFound)?;
+    let meta: project::Metadata = project.try_into().map_err(Error::Project)?;
+    let repo = git2::Repository::open_bare(ctx.paths.git_dir()).map_err(Error::from)?;
+    match get_head_commit(&repo, urn, revision.as_str(), &meta.delegates) {
+        Ok(commit) => One::from_str(&commit.id.to_string()).map_err(|_| Error::NotFound),
+        Err(_) if git::Oid::from_str(revision.as_str()).is_ok() => Ok(revision),
+        Err(err) => Err(err),
+    }
+}
+
+async fn browse<T, F>(reference: Reference<Single>, ctx: &Context, callback: F) -> Result<T, Error>
 where
-    F: FnOnce(&mut git::Browser) -> Result<T, radicle_source::Error> + Send,
+    T: Send + 'static,
+    F: FnOnce(&mut git::Browser) -> Result<T, radicle_source::Error> + Send + 'static,
 {
     let namespace = git::namespace::Namespace::try_from(
         reference
@@ -633,13 +1325,37 @@ where
             .try_into()
             .map_err(|_| Error::NotFound)?,
     };
-    let repo = git::Repository::new(paths.git_dir())?;
-    let mut browser = git::Browser::new_with_namespace(&repo, &namespace, revision)?;
+    let paths = ctx.paths.clone();
+
+    blocking(ctx, move || {
+        let repo = git::Repository::new(paths.git_dir())?;
+        let mut browser = git::Browser::new_with_namespace(&repo, &namespace, revision)?;
 
-    callback(&mut browser).map_err(|err| match &err {
-        radicle_source::error::Error::PathNotFound(_) => Error::NotFound,
-        _ => Error::from(err),
+        callback(&mut browser).map_err(|err| match &err {
+            radicle_source::error::Error::PathNotFound(_) => Error::NotFound,
+            _ => Error::from(err),
+        })
     })
+    .await
+}
+
+/// Run work that blocks, eg. on git, on the blocking thread pool, waiting at most for the
+/// configured browse timeout.
+async fn blocking<T, F>(ctx: &Context, work: F) -> Result<T, Error>
+where
+    T: Send + 'static,
+    F: FnOnce() -> Result<T, Error> + Send + 'static,
+{
+    // The blocking task can't be interrupted, so on timeout it runs to completion in the
+    // background, but the request no longer waits for it.
+    tokio::time::timeout(ctx.browse_timeout, tokio::task::spawn_blocking(work))
+        .await
+        .map_err(|_| Error::Timeout)??
+}
+
+/// Check whether a revision is a full commit hash.
+fn is_oid(revision: &str) -> bool {
+    revision.len() == 40 && git2::Oid::from_str(revision).is_ok()
 }
 
 fn remote_branch(branch_name: &str, peer_id: &PeerId) -> git::Branch {
@@ -871,7 +1587,46 @@ mod routes {
     }
 
     #[tokio::test]
-    async fn test_project_route() {
+    async fn test_projects_root_route_links() {
+        let (profile, signer, _, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/projects?per-page=1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::LINK).unwrap(),
+            "</projects?per-page=1&page=1>; rel=\"next\""
+        );
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/projects?per-page=1&page=1")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers().get(header::LINK).unwrap(),
+            "</projects?per-page=1&page=0>; rel=\"prev\""
+        );
+    }
+
+    #[tokio::test]
+    async fn test_project_route() {
         let (profile, signer, project, _) = setup::env();
         let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
         let app = router(ctx);
@@ -908,6 +1663,39 @@ mod routes {
         assert_eq!(alias_body, urn_body);
     }
 
+    #[tokio::test]
+    async fn test_project_route_not_modified() {
+        let (profile, signer, project, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}", project.urn()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        let etag = response.headers().get(header::ETAG).unwrap().clone();
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}", project.urn()))
+                    .header(header::IF_NONE_MATCH, etag)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_MODIFIED);
+    }
+
     #[tokio::test]
     async fn test_commits_route() {
         let (profile, signer, project, head) = setup::env();
@@ -931,6 +1719,7 @@ mod routes {
 
         assert_eq!(body["headers"][0]["header"]["summary"], COMMIT_MSG);
         assert_eq!(body["headers"][0]["header"]["sha1"], head.to_string());
+        assert_eq!(body["total"], 1);
 
         let response = app
             .clone()
@@ -949,6 +1738,138 @@ mod routes {
         assert_eq!(body["header"]["summary"], COMMIT_MSG);
     }
 
+    #[tokio::test]
+    async fn test_commits_route_unknown_author() {
+        let (profile, signer, project, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/projects/{}/commits?author=SHA256:unknown",
+                        project.urn()
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::FOUND);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["headers"], json!([]));
+        assert_eq!(body.get("total"), None);
+    }
+
+    #[tokio::test]
+    async fn test_commits_route_stats() {
+        let (profile, signer, project, head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/projects/{}/commits?parent={}&stats=true",
+                        project.urn(),
+                        head
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["headers"][0]["stats"]["additions"], 2);
+        assert_eq!(body["headers"][0]["stats"]["deletions"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_commit_refs_route() {
+        let (profile, signer, project, head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}/commits/{}/refs", project.urn(), head))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert!(!body["refs"].as_array().unwrap().is_empty());
+        assert_eq!(body["truncated"], false);
+    }
+
+    #[tokio::test]
+    async fn test_commit_diff_route() {
+        let (profile, signer, project, head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}/commits/{}/diff", project.urn(), head))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["stats"]["additions"], 2);
+        assert_eq!(body["stats"]["deletions"], 0);
+        assert!(body.get("branches").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_activity_route_bucket() {
+        let (profile, signer, project, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/projects/{}/activity?format=iso&bucket=day",
+                        project.urn()
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let bucket = &body["activity"][0];
+
+        assert!(bucket["count"].as_u64().unwrap() > 0);
+        assert!(bucket["date"].as_str().unwrap().ends_with("T00:00:00Z"));
+    }
+
     #[tokio::test]
     async fn test_tree_route() {
         let (profile, signer, project, head) = setup::env();
@@ -973,6 +1894,71 @@ mod routes {
         assert_eq!(body["entries"][0]["path"], COMMIT_FILE_NAME);
     }
 
+    #[tokio::test]
+    async fn test_tree_route_recursive() {
+        let (profile, signer, project, head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/projects/{}/tree/{}/?recursive=true",
+                        project.urn(),
+                        head
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["entries"][0]["path"], COMMIT_FILE_NAME);
+        assert_eq!(body["entries"][0]["kind"], "blob");
+        assert_eq!(body["truncated"], false);
+    }
+
+    #[tokio::test]
+    async fn test_tree_route_branch() {
+        let (profile, signer, project, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}/tree/master/", project.urn()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["entries"][0]["path"], COMMIT_FILE_NAME);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}/tree/unknown/", project.urn()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn test_blob_route() {
         let (profile, signer, project, head) = setup::env();
@@ -1003,6 +1989,81 @@ mod routes {
         assert_eq!(body["content"], COMMIT_FILE_CONTENT);
     }
 
+    #[tokio::test]
+    async fn test_raw_route() {
+        let (profile, signer, project, head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let uri = format!(
+            "/projects/{}/raw/{}/{}",
+            project.urn(),
+            head,
+            COMMIT_FILE_NAME
+        );
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(&uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], COMMIT_FILE_CONTENT.as_bytes());
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(&uri)
+                    .header(header::RANGE, "bytes=3-")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 3-5/6");
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        assert_eq!(&body[..], b"Bob");
+
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(&uri)
+                    .header(header::RANGE, "bytes=10-20")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+
+        // The commit can't be read through another project.
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!(
+                        "/projects/rad:git:hnrkfbrd7y9674d8ow8uioki16fniwcyoz67y/raw/{}/{}",
+                        head, COMMIT_FILE_NAME
+                    ))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+
     #[tokio::test]
     async fn test_readme_route() {
         let (profile, signer, project, head) = setup::env();
@@ -1028,6 +2089,30 @@ mod routes {
         assert_eq!(body["content"], COMMIT_README_CONTENT);
     }
 
+    #[tokio::test]
+    async fn test_readme_route_head() {
+        let (profile, signer, project, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}/readme", project.urn()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["path"], "README");
+        assert_eq!(body["content"], COMMIT_README_CONTENT);
+    }
+
     #[tokio::test]
     async fn test_patches_route() {
         let (profile, signer, project, _head) = setup::env();
@@ -1105,4 +2190,67 @@ mod routes {
 
         assert_eq!(response.status(), StatusCode::OK);
     }
+
+    #[tokio::test]
+    async fn test_cache_purge_route_unauthorized() {
+        let (profile, signer, project, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri(format!("/projects/{}/cache/purge", project.urn()))
+                    .header(header::AUTHORIZATION, "Bearer unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_issues_route_state() {
+        let (profile, signer, project, _head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+
+        for (state, count) in [("open", 1), ("closed", 0)] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!(
+                            "/projects/{}/issues?state={}",
+                            project.urn(),
+                            state
+                        ))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), StatusCode::OK);
+
+            let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+            let body: Value = serde_json::from_slice(&body).unwrap();
+
+            assert_eq!(body.as_array().unwrap().len(), count);
+        }
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/projects/{}/issues?state=unknown", project.urn()))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
 }
@@ -1,6 +1,5 @@
 use std::collections::HashMap;
 use std::convert::TryInto;
-use std::env;
 use std::iter::repeat_with;
 use std::str::FromStr;
 use std::time::Duration;
@@ -10,24 +9,33 @@ use axum::routing::{get, post};
 use axum::{Extension, Json, Router};
 use chrono::{DateTime, Utc};
 use ethers_core::utils::hex;
-use hyper::http::uri::Authority;
+use http_body::Limited;
+use hyper::{Body, StatusCode};
 use serde_json::json;
 use siwe::Message;
+use tower_http::limit::RequestBodyLimitLayer;
 
 use crate::auth::{AuthRequest, AuthState, Session};
-use crate::axum_extra::Path;
+use crate::axum_extra::{self, Path};
 use crate::{Context, Error};
 
 pub const UNAUTHORIZED_SESSIONS_EXPIRATION: Duration = Duration::from_secs(60);
 
 pub fn router(ctx: Context) -> Router {
-    Router::new()
+    let limit = ctx.session_body_limit;
+    // Sessions are created and signed in without authentication, so request bodies are bounded.
+    let routes: Router<Limited<Body>> = Router::new()
         .route("/sessions", post(session_create_handler))
         .route(
             "/sessions/:id",
-            get(session_get_handler).put(session_signin_handler),
+            get(session_get_handler)
+                .put(session_signin_handler)
+                .patch(session_refresh_handler)
+                .delete(session_delete_handler),
         )
-        .layer(Extension(ctx))
+        .layer(Extension(ctx));
+
+    routes.layer(RequestBodyLimitLayer::new(limit))
 }
 
 /// Create session.
@@ -68,25 +76,25 @@ async fn session_get_handler(
 async fn session_signin_handler(
     Extension(ctx): Extension<Context>,
     Path(id): Path<String>,
-    Json(request): Json<AuthRequest>,
+    axum_extra::Json(request): axum_extra::Json<AuthRequest>,
 ) -> impl IntoResponse {
     // Get unauthenticated session data, return early if not found
     let mut sessions = ctx.sessions.write().await;
     let session = sessions.get(&id).ok_or(Error::NotFound)?;
 
     if let AuthState::Unauthorized { nonce, .. } = session {
+        if ctx.siwe_domains.is_empty() {
+            return Err(Error::Auth("Sign-In With Ethereum is disabled"));
+        }
         let message = Message::from_str(request.message.as_str()).map_err(Error::from)?;
 
-        let host = env::var("RADICLE_DOMAIN").map_err(Error::from)?;
-
         // Validate nonce
         if *nonce != message.nonce {
             return Err(Error::Auth("Invalid nonce"));
         }
 
-        // Verify that domain is the correct one
-        let authority = Authority::from_str(&host).map_err(|_| Error::Auth("Invalid host"))?;
-        if authority != message.domain {
+        // Verify that domain is one of the allowed ones
+        if !ctx.siwe_domains.contains(&message.domain) {
             return Err(Error::Auth("Invalid domain"));
         }
 
@@ -107,6 +115,50 @@ async fn session_signin_handler(
     Err(Error::Auth("Session already authorized"))
 }
 
+/// Refresh session, extending its expiration time.
+/// `PATCH /sessions/:id`
+async fn session_refresh_handler(
+    Extension(ctx): Extension<Context>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    // The write lock is held for the whole update, so that concurrent refreshes can't race.
+    let mut sessions = ctx.sessions.write().await;
+    let session = sessions.get_mut(&id).ok_or(Error::NotFound)?;
+
+    if let AuthState::Authorized(session) = session {
+        let now = Utc::now();
+
+        if let Some(expiration_time) = session.expiration_time {
+            if now >= expiration_time {
+                return Err(Error::Auth("Session expired"));
+            }
+            let ttl = chrono::Duration::from_std(ctx.session_ttl)
+                .map_err(|_| Error::Auth("Invalid session TTL"))?;
+
+            // Sessions are only ever extended, never shortened.
+            session.expiration_time = Some(expiration_time.max(now + ttl));
+        }
+
+        return Ok::<_, Error>(Json(json!({ "id": id, "session": session })));
+    }
+
+    Err(Error::Auth("Session not authorized"))
+}
+
+/// Delete session.
+/// `DELETE /sessions/:id`
+async fn session_delete_handler(
+    Extension(ctx): Extension<Context>,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let mut sessions = ctx.sessions.write().await;
+    // Sessions are removed regardless of their state, so that signing out of an expired
+    // or not yet authorized session also succeeds.
+    sessions.remove(&id).ok_or(Error::NotFound)?;
+
+    Ok::<_, Error>(StatusCode::NO_CONTENT)
+}
+
 fn create_session(
     map: &mut HashMap<String, AuthState>,
     expiration_time: DateTime<Utc>,
@@ -126,3 +178,98 @@ fn create_session(
 
     (id, nonce)
 }
+
+#[cfg(test)]
+mod routes {
+    use axum::body::Body;
+    use axum::http::Request;
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_extra::setup;
+
+    const THEME: &str = "base16-ocean.dark";
+
+    #[tokio::test]
+    async fn test_session_delete_route() {
+        let (profile, signer, _, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/sessions")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        let id = body["id"].as_str().unwrap();
+
+        for status in [StatusCode::NO_CONTENT, StatusCode::NOT_FOUND] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method("DELETE")
+                        .uri(format!("/sessions/{}", id))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+
+            assert_eq!(response.status(), status);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_session_signin_route_body_too_large() {
+        let (profile, signer, _, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let body = vec![b' '; crate::SESSION_BODY_LIMIT + 1];
+        let response = router(ctx)
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/sessions/unknown")
+                    .header("Content-Type", "application/json")
+                    .header("Content-Length", body.len())
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_session_signin_route_body_too_large_without_length() {
+        let (profile, signer, _, _) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let body = vec![b' '; crate::SESSION_BODY_LIMIT + 1];
+        // Without a `Content-Length`, the limit is only hit while the body is read.
+        let response = router(ctx)
+            .oneshot(
+                Request::builder()
+                    .method("PUT")
+                    .uri("/sessions/unknown")
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+}
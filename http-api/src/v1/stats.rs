@@ -4,6 +4,8 @@ use axum::{Extension, Json, Router};
 use librad::git::identities::{self, SomeIdentity};
 use serde_json::json;
 
+use radicle_common::{cobs, person};
+
 use crate::Context;
 use crate::Error;
 
@@ -16,16 +18,44 @@ pub fn router(ctx: Context) -> Router {
 /// Return the stats for the node.
 /// `GET /stats`
 async fn stats_handler(Extension(ctx): Extension<Context>) -> impl IntoResponse {
+    let mut stats = ctx.stats.write().await;
+    if let Some((computed, value)) = &*stats {
+        if computed.elapsed() < crate::STATS_TTL {
+            return Ok::<_, Error>(Json(value.clone()));
+        }
+    }
+
     let storage = ctx.storage().await?;
-    let (projects, persons): (Vec<_>, Vec<_>) = identities::any::list(storage.read_only())
+    let whoami = person::local(&*storage).map_err(Error::LocalIdentity)?;
+    let store = cobs::Store::new(whoami, &ctx.paths, &storage);
+    let (issues, patches) = (store.issues(), store.patches());
+    let (mut projects, mut persons) = (0, 0);
+    let (mut issue_count, mut patch_count) = (0, 0);
+
+    for identity in identities::any::list(storage.read_only())
         .map_err(Error::from)?
-        .partition(|identity| match identity {
-            Ok(SomeIdentity::Project(_)) => true,
-            Ok(SomeIdentity::Person(_)) => false,
-            _ => panic!("Error while listing identities"),
-        });
-
-    Ok::<_, Error>(Json(
-        json!({ "projects": { "count": projects.len() }, "users": { "count": persons.len() } }),
-    ))
+        .flatten()
+    {
+        match identity {
+            SomeIdentity::Project(project) => {
+                let urn = project.urn();
+
+                projects += 1;
+                issue_count += issues.count(&urn).map_err(Error::Cobs)?;
+                patch_count += patches.count(&urn).map_err(Error::Cobs)?;
+            }
+            SomeIdentity::Person(_) => persons += 1,
+            _ => {}
+        }
+    }
+
+    let value = json!({
+        "projects": { "count": projects },
+        "users": { "count": persons },
+        "issues": { "count": issue_count },
+        "patches": { "count": patch_count },
+    });
+    *stats = Some((std::time::Instant::now(), value.clone()));
+
+    Ok::<_, Error>(Json(value))
 }
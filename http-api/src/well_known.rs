@@ -0,0 +1,85 @@
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Extension, Json, Router};
+use serde_json::json;
+
+use crate::axum_extra::Path;
+use crate::{Context, Error};
+
+pub fn router(ctx: Context) -> Router {
+    Router::new()
+        .route(
+            "/.well-known/radicle/:alias",
+            get(project_discovery_handler),
+        )
+        .layer(Extension(ctx))
+}
+
+/// Discover a project by its alias.
+/// `GET /.well-known/radicle/:alias`
+async fn project_discovery_handler(
+    Extension(ctx): Extension<Context>,
+    Path(alias): Path<String>,
+) -> impl IntoResponse {
+    let urn = ctx.resolve_alias(&alias).await?;
+    let info = ctx.project_info(urn).await?;
+    let response = json!({
+        "urn": info.meta.urn,
+        "defaultBranch": info.meta.default_branch,
+        "head": info.head.map(|h| h.to_string()),
+    });
+
+    Ok::<_, Error>(Json(response))
+}
+
+#[cfg(test)]
+mod routes {
+    use axum::body::Body;
+    use axum::http::{Request, StatusCode};
+    use serde_json::Value;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::test_extra::setup;
+
+    const THEME: &str = "base16-ocean.dark";
+    const PROJECT_NAME: &str = "nakamoto";
+
+    #[tokio::test]
+    async fn test_project_discovery_route() {
+        let (profile, signer, project, head) = setup::env();
+        let ctx = Context::new(profile.paths().to_owned(), signer, THEME.to_string());
+        let app = router(ctx);
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri(format!("/.well-known/radicle/{}", PROJECT_NAME))
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        assert_eq!(body["urn"], project.urn().to_string());
+        assert_eq!(body["defaultBranch"], "master");
+        assert_eq!(body["head"], head.to_string());
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/.well-known/radicle/unknown")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+}
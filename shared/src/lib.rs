@@ -2,7 +2,10 @@ pub mod identity;
 pub mod signer;
 
 mod logging;
-pub use logging::init_logger;
+pub use logging::{init_logger, LogFmt};
+
+mod shutdown;
+pub use shutdown::shutdown_signal;
 
 use std::path::PathBuf;
 
@@ -1,13 +1,57 @@
+use std::str::FromStr;
+
 use tracing::dispatcher::{self, Dispatch};
 use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::EnvFilter;
 use tracing_subscriber::Registry;
 
-pub fn init_logger() {
-    let subscriber = Registry::default()
-        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
-        .with(tracing_logfmt::layer());
+/// Log output format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFmt {
+    /// Human readable `key=value` lines.
+    Plain,
+    /// One JSON object per line, for ingestion by Google Cloud Logging.
+    Gcp,
+}
+
+impl Default for LogFmt {
+    fn default() -> Self {
+        Self::Plain
+    }
+}
+
+impl FromStr for LogFmt {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "plain" => Ok(Self::Plain),
+            "gcp" => Ok(Self::Gcp),
+            _ => Err(format!(
+                "unknown log format '{}', expected 'plain' or 'gcp'",
+                s
+            )),
+        }
+    }
+}
+
+pub fn init_logger(format: LogFmt) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info"));
+    let dispatch = match format {
+        LogFmt::Plain => Dispatch::new(
+            Registry::default()
+                .with(filter)
+                .with(tracing_logfmt::layer()),
+        ),
+        LogFmt::Gcp => Dispatch::new(
+            Registry::default().with(filter).with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .flatten_event(true)
+                    .with_current_span(false),
+            ),
+        ),
+    };
 
-    dispatcher::set_global_default(Dispatch::new(subscriber))
-        .expect("Global logger has already been set!");
+    dispatcher::set_global_default(dispatch).expect("Global logger has already been set!");
 }
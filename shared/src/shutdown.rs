@@ -0,0 +1,30 @@
+use tokio::signal;
+
+/// Wait until the process is asked to terminate, ie. on `SIGTERM` or `SIGINT`.
+///
+/// Services use this to stop accepting connections and let in-flight requests complete.
+pub async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(err) = signal::ctrl_c().await {
+            tracing::error!("Failed to listen for SIGINT: {}", err);
+            std::future::pending::<()>().await
+        }
+    };
+    let terminate = async {
+        match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+            Ok(mut stream) => {
+                stream.recv().await;
+            }
+            Err(err) => {
+                tracing::error!("Failed to listen for SIGTERM: {}", err);
+                std::future::pending::<()>().await
+            }
+        }
+    };
+
+    tokio::select! {
+        _ = interrupt => {},
+        _ = terminate => {},
+    }
+    tracing::info!("Shutting down, waiting for in-flight requests to complete...");
+}